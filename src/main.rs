@@ -1,12 +1,146 @@
+use std::env;
+use std::path::{Path, PathBuf};
+use std::process;
+
 use ggez::event;
+use ggez::GameError;
 use ggez::GameResult;
+use main_state::export::Renderer;
+use main_state::generator;
+use main_state::metrics;
+use main_state::replay;
 use main_state::MainState;
 
 mod main_state;
 
+const USAGE: &str = "usage:
+    trapped_level_editor [--resources <dir>]
+    trapped_level_editor [--resources <dir>] export <level> <png>
+    trapped_level_editor [--resources <dir>] thumbnails <level dir> [<png dir>]
+    trapped_level_editor replay <level or level dir>...
+    trapped_level_editor stats <level or level dir>...
+    trapped_level_editor [--resources <dir>] generate <level dir> [<count> [<seed>]]";
+
+struct Args {
+    resources: PathBuf,
+    command: Vec<String>,
+}
+
+// `resources` next to the executable wins, then the one in the project directory
+// for binaries built with cargo, then `--resources <dir>`. Everything else is the command.
+fn parse_args() -> Args {
+    let mut resources = None;
+    let mut command = Vec::new();
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--resources" => {
+                let Some(dir) = args.next() else {
+                    eprintln!("{}", USAGE);
+                    process::exit(2);
+                };
+                // ggez puts relative paths next to the executable, not the working directory
+                resources = Some(env::current_dir().unwrap_or_default().join(dir));
+            }
+            _ => command.push(arg),
+        }
+    }
+
+    let next_to_exe = env::current_exe()
+        .ok()
+        .and_then(|exe| Some(exe.parent()?.join("resources")));
+    let in_project = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("resources");
+
+    let found = next_to_exe
+        .into_iter()
+        .chain([in_project.clone()])
+        .find(|dir| dir.is_dir());
+    // Without any directory the editor starts with placeholders.
+    let resources = found.or(resources).unwrap_or(in_project);
+
+    Args { resources, command }
+}
+
+fn renderer(resources: &Path) -> Renderer {
+    let (renderer, problems) = Renderer::new(resources);
+    for problem in problems {
+        eprintln!("warning: {}", problem);
+    }
+
+    renderer
+}
+
+// Command line tools print errors for people rather than the debug form `main` would.
+fn exit_on_error(result: GameResult) -> GameResult {
+    if let Err(error) = result {
+        match error {
+            GameError::CustomError(message) => eprintln!("error: {}", message),
+            error => eprintln!("error: {}", error),
+        }
+        process::exit(1);
+    }
+
+    Ok(())
+}
+
 pub fn main() -> GameResult {
-    let cb = ggez::ContextBuilder::new("level-editor", "vil'mo");
-    let (ctx, event_loop) = cb.build()?;
-    let state = MainState::new(&ctx)?;
-    event::run(ctx, event_loop, state)
+    let args = parse_args();
+    let command = args.command.iter().map(String::as_str).collect::<Vec<_>>();
+
+    match command[..] {
+        [] => {
+            let cb = ggez::ContextBuilder::new("level-editor", "vil'mo")
+                .resources_dir_name(args.resources);
+            let (ctx, event_loop) = cb.build()?;
+            let state = MainState::new(&ctx)?;
+            event::run(ctx, event_loop, state)
+        }
+
+        ["export", level, png] => {
+            exit_on_error(renderer(&args.resources).export(Path::new(level), Path::new(png)))
+        }
+        ["thumbnails", dir] => {
+            let dir = Path::new(dir);
+            exit_on_error(renderer(&args.resources).export_directory(dir, &dir.join("thumbnails")))
+        }
+        ["thumbnails", dir, out_dir] => exit_on_error(
+            renderer(&args.resources).export_directory(Path::new(dir), Path::new(out_dir)),
+        ),
+
+        ["replay", ref levels @ ..] if !levels.is_empty() => {
+            let levels = levels.iter().map(PathBuf::from).collect::<Vec<_>>();
+            exit_on_error(replay::check_files(&levels))
+        }
+
+        ["stats", ref levels @ ..] if !levels.is_empty() => {
+            let levels = levels.iter().map(PathBuf::from).collect::<Vec<_>>();
+            exit_on_error(metrics::print_files(&levels))
+        }
+
+        ["generate", dir, ref numbers @ ..] if numbers.len() <= 2 => {
+            let numbers = numbers
+                .iter()
+                .map(|number| number.parse::<u64>())
+                .collect::<Result<Vec<_>, _>>();
+            let Ok(numbers) = numbers else {
+                eprintln!("{}", USAGE);
+                process::exit(2);
+            };
+
+            let count = numbers.first().copied().unwrap_or(1);
+            let seed = numbers.get(1).copied().unwrap_or_else(generator::time_seed);
+            exit_on_error(generator::generate_files(
+                &args.resources,
+                Path::new(dir),
+                count,
+                seed,
+            ))
+        }
+
+        _ => {
+            eprintln!("{}", USAGE);
+            process::exit(2);
+        }
+    }
 }
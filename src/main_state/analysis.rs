@@ -0,0 +1,355 @@
+use std::collections::{HashMap, HashSet};
+
+use ggez::{
+    glam::IVec2,
+    graphics::{Canvas, Color, DrawMode, DrawParam, Mesh, MeshBuilder, Rect},
+    Context, GameResult,
+};
+
+use super::{
+    document::Document,
+    game::{edge_wall, Game, Move},
+    instances::{floor::FloorType, object::ObjectType, ActivatingColor},
+    level_data::LevelData,
+    MainState,
+};
+
+const UNREACHABLE_COLOR: Color = Color::new(0.6, 0.2, 1.0, 0.45);
+const DEAD_SQUARE_COLOR: Color = Color::new(1.0, 0.5, 0.0, 0.3);
+const STUCK_BOX_COLOR: Color = Color::new(1.0, 0.0, 0.0, 0.55);
+const WEAK_FLOOR_COLOR: Color = Color::new(1.0, 1.0, 0.0, 0.45);
+
+// Problems found without playing the level, so they show up right while building it.
+// Everything is judged optimistically: boxes never get in the way, and walls that
+// a button of their color can switch count as open.
+#[derive(Debug, Default, Clone)]
+pub struct Analysis {
+    // Floors the player can't get to from the start.
+    pub unreachable: HashSet<IVec2>,
+    // Floors a box can never be pushed away from, except buttons, where
+    // boxes usually are meant to stay.
+    pub dead_squares: HashSet<IVec2>,
+    // Boxes standing on dead squares.
+    pub stuck_boxes: HashSet<IVec2>,
+    // Fragile floors that the player has to step on more often than they hold.
+    pub weak_floors: HashSet<IVec2>,
+}
+
+impl Analysis {
+    pub fn new(level_data: &LevelData) -> Analysis {
+        let graph = Graph::new(level_data);
+        let mut analysis = Analysis::default();
+
+        for pos in level_data.floors.keys() {
+            let movable = Move::ALL.into_iter().any(|m| graph.pushable(*pos, m));
+            let button = level_data.floors[pos].floor_type == FloorType::Button;
+
+            if !movable && !button {
+                analysis.dead_squares.insert(*pos);
+            }
+        }
+
+        for (pos, object) in &level_data.objects {
+            if object.object_type != ObjectType::Player && analysis.dead_squares.contains(pos) {
+                analysis.stuck_boxes.insert(*pos);
+            }
+        }
+
+        // Reachability only makes sense for playable levels.
+        let Ok(game) = Game::new(level_data) else {
+            return analysis;
+        };
+        let player = game.start().player;
+
+        let reachable = graph.reach(player, None);
+        analysis.unreachable = level_data
+            .floors
+            .keys()
+            .filter(|pos| !reachable.contains(pos))
+            .copied()
+            .collect();
+
+        let wins = level_data.collectibles.keys().copied().collect::<Vec<_>>();
+
+        for (pos, floor) in &level_data.floors {
+            if floor.floor_type != FloorType::Normal
+                || floor.is_indestructible()
+                || !reachable.contains(pos)
+            {
+                continue;
+            }
+
+            // Every part of the level that can only be entered through this floor and
+            // holds a win has to be walked into from it. Only leaving a floor wears it
+            // down, so it has to hold one departure into each of those parts.
+            let mut seen = match *pos == player {
+                true => HashSet::new(),
+                false => graph.reach(player, Some(*pos)),
+            };
+            let mut parts = 0;
+
+            for next in graph.neighbors(*pos, None) {
+                if seen.contains(&next) {
+                    continue;
+                }
+
+                let part = graph.reach(next, Some(*pos));
+                if wins.iter().any(|win| part.contains(win)) {
+                    parts += 1;
+                }
+                seen.extend(part);
+            }
+
+            if floor.durability < parts {
+                analysis.weak_floors.insert(*pos);
+            }
+        }
+
+        analysis
+    }
+
+    pub fn summary(&self) -> String {
+        format!(
+            "analysis: {} unreachable, {} dead squares, {} stuck boxes, {} weak floors",
+            self.unreachable.len(),
+            self.dead_squares.len(),
+            self.stuck_boxes.len(),
+            self.weak_floors.len()
+        )
+    }
+}
+
+// Floors connected by the edges the player could ever walk through.
+struct Graph<'a> {
+    level_data: &'a LevelData,
+    buttons: HashSet<ActivatingColor>,
+    teleports: HashMap<IVec2, IVec2>,
+}
+
+impl Graph<'_> {
+    fn new(level_data: &LevelData) -> Graph<'_> {
+        let buttons = level_data
+            .floors
+            .values()
+            .filter(|floor| floor.floor_type == FloorType::Button)
+            .map(|floor| floor.color)
+            .collect();
+
+        Graph {
+            level_data,
+            buttons,
+            teleports: Game::pair_teleports(level_data),
+        }
+    }
+
+    fn open(&self, pos: IVec2, m: Move) -> bool {
+        edge_wall(self.level_data, pos, m).is_none_or(|wall| {
+            wall.opened || (wall.input_dependent && self.buttons.contains(&wall.color))
+        })
+    }
+
+    fn walkable(&self, pos: IVec2, m: Move) -> bool {
+        self.level_data.floors.contains_key(&(pos + m.offset())) && self.open(pos, m)
+    }
+
+    // Whether a box at `pos` can be pushed in direction `m` by a player standing behind it.
+    fn pushable(&self, pos: IVec2, m: Move) -> bool {
+        let behind = pos - m.offset();
+
+        self.level_data.floors.contains_key(&behind)
+            && self.walkable(behind, m)
+            && self.walkable(pos, m)
+    }
+
+    fn neighbors(&self, pos: IVec2, removed: Option<IVec2>) -> Vec<IVec2> {
+        Move::ALL
+            .into_iter()
+            .filter(|m| self.walkable(pos, *m))
+            .map(|m| pos + m.offset())
+            .chain(self.teleports.get(&pos).copied())
+            .filter(|next| Some(*next) != removed)
+            .collect()
+    }
+
+    // Floors reachable from `start` without stepping on `removed`.
+    fn reach(&self, start: IVec2, removed: Option<IVec2>) -> HashSet<IVec2> {
+        let mut reached = HashSet::from([start]);
+        let mut stack = vec![start];
+
+        while let Some(pos) = stack.pop() {
+            for next in self.neighbors(pos, removed) {
+                if reached.insert(next) {
+                    stack.push(next);
+                }
+            }
+        }
+
+        reached
+    }
+}
+
+// Tints the problem cells on the canvas. The analysis is only done again when
+// the level has changed and no mouse button is held.
+#[derive(Debug, Default)]
+pub struct AnalysisOverlay {
+    pub visible: bool,
+    // The analysis with the document id and revision it was done for.
+    computed: Option<((u64, u64), Analysis)>,
+}
+
+impl AnalysisOverlay {
+    pub fn new() -> AnalysisOverlay {
+        AnalysisOverlay::default()
+    }
+
+    pub fn toggle(&mut self) {
+        self.visible = !self.visible;
+    }
+
+    pub fn update(&mut self, document: &Document, painting: bool) {
+        if !self.visible || painting {
+            return;
+        }
+
+        let key = (document.id, document.revision);
+        if self
+            .computed
+            .as_ref()
+            .is_none_or(|(computed, _)| *computed != key)
+        {
+            self.computed = Some((key, Analysis::new(&document.level_data)));
+        }
+    }
+
+    pub fn analysis(&self) -> Option<&Analysis> {
+        match self.visible {
+            true => self.computed.as_ref().map(|(_, analysis)| analysis),
+            false => None,
+        }
+    }
+
+    // Draws in world coordinates.
+    pub fn draw(&self, ctx: &Context, canvas: &mut Canvas) -> GameResult {
+        let Some(analysis) = self.analysis() else {
+            return Ok(());
+        };
+
+        let size = MainState::CELL_SIZE as f32;
+        let mut builder = MeshBuilder::new();
+        let mut empty = true;
+
+        for (cells, color) in [
+            (&analysis.unreachable, UNREACHABLE_COLOR),
+            (&analysis.dead_squares, DEAD_SQUARE_COLOR),
+            (&analysis.weak_floors, WEAK_FLOOR_COLOR),
+            (&analysis.stuck_boxes, STUCK_BOX_COLOR),
+        ] {
+            for pos in cells {
+                let rect = Rect::new(pos.x as f32 * size, pos.y as f32 * size, size, size);
+                builder.rectangle(DrawMode::fill(), rect, color)?;
+                empty = false;
+            }
+        }
+
+        if !empty {
+            let mesh = Mesh::from_data(ctx, builder.build());
+            canvas.draw(&mesh, DrawParam::default());
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::main_state::serialization;
+
+    fn analyze(lines: &[&str]) -> Analysis {
+        Analysis::new(&serialization::from_str(&format!(
+            "0,0\n{}",
+            lines.join("\n")
+        )))
+    }
+
+    fn cells(cells: &[(i32, i32)]) -> HashSet<IVec2> {
+        cells.iter().map(|(x, y)| IVec2::new(*x, *y)).collect()
+    }
+
+    // A row of three floors with the player on the left and the win on the right.
+    const ROW: [&str; 5] = [
+        "floor 0,0",
+        "floor 1,0",
+        "floor 2,0",
+        "player 0,0",
+        "win 2,0",
+    ];
+
+    #[test]
+    fn finds_floors_the_player_cant_get_to() {
+        let analysis = analyze(&[&ROW[..], &["floor 5,0"]].concat());
+        assert_eq!(analysis.unreachable, cells(&[(5, 0)]));
+
+        let analysis = analyze(&[&ROW[..], &["wall 0,0 r closed:true"]].concat());
+        assert_eq!(analysis.unreachable, cells(&[(1, 0), (2, 0)]));
+    }
+
+    #[test]
+    fn walls_a_button_can_open_count_as_open() {
+        let analysis = analyze(
+            &[
+                &ROW[..],
+                &[
+                    "button 0,1 c:r",
+                    "wall 0,0 r c:r closed:true inputbased:true",
+                ],
+            ]
+            .concat(),
+        );
+
+        assert!(analysis.unreachable.is_empty());
+    }
+
+    #[test]
+    fn finds_dead_squares_and_stuck_boxes() {
+        // A room of three by three floors with a button in the lower right corner.
+        let analysis = analyze(&[
+            "floor 0,0",
+            "floor 1,0",
+            "floor 2,0",
+            "floor 0,1",
+            "floor 1,1",
+            "floor 2,1",
+            "floor 0,2",
+            "floor 1,2",
+            "button 2,2",
+            "player 1,0",
+            "box 0,0",
+            "box 1,1",
+            "win 1,2",
+        ]);
+
+        // The corners, except for the button.
+        assert_eq!(analysis.dead_squares, cells(&[(0, 0), (2, 0), (0, 2)]));
+        assert_eq!(analysis.stuck_boxes, cells(&[(0, 0)]));
+    }
+
+    #[test]
+    fn finds_fragile_floors_left_more_often_than_they_hold() {
+        // From the start below, both wins are reached through the floor between them.
+        let level = |durability| {
+            analyze(&[
+                "floor 0,0",
+                &format!("floor 1,0 dur:{}", durability),
+                "floor 2,0",
+                "floor 1,1",
+                "player 1,1",
+                "win 0,0",
+                "win 2,0",
+            ])
+        };
+
+        assert_eq!(level(1).weak_floors, cells(&[(1, 0)]));
+        assert!(level(2).weak_floors.is_empty());
+    }
+}
@@ -0,0 +1,266 @@
+use std::{
+    fs::{File, TryLockError},
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use ggez::GameResult;
+
+use super::{document::Document, serialization};
+
+const INTERVAL: Duration = Duration::from_secs(30);
+const PATH_PREFIX: &str = "#path:";
+
+// Periodically writes every document with unsaved changes into the recovery
+// directory. A clean save, a discard or a normal exit removes the copies.
+// Every running editor holds a lock on `{session}.lock` there, so files left by
+// a session whose lock can be taken belong to one that crashed. Sessions are
+// named after the process id and the start time, because process ids get reused.
+pub struct Autosave {
+    dir: PathBuf,
+    session: String,
+    last: Instant,
+    // Kept open for as long as the editor runs, the system drops the lock when it exits.
+    _lock: Option<File>,
+}
+
+impl Autosave {
+    pub fn new(dir: PathBuf) -> Autosave {
+        let started = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |time| time.as_nanos());
+        let session = format!("{}_{}", std::process::id(), started);
+
+        let lock = std::fs::create_dir_all(&dir)
+            .and_then(|_| File::create(dir.join(format!("{}.lock", session))))
+            .ok()
+            .filter(|file| file.try_lock().is_ok());
+
+        Autosave {
+            dir,
+            session,
+            last: Instant::now(),
+            _lock: lock,
+        }
+    }
+
+    // Whether the session still runs, which only its lock tells. Without a lock
+    // file the session can't be running.
+    fn owner_alive(&self, session: &str) -> bool {
+        let Ok(file) = File::open(self.dir.join(format!("{}.lock", session))) else {
+            return false;
+        };
+
+        match file.try_lock() {
+            Ok(()) => false,
+            Err(TryLockError::WouldBlock) => true,
+            // Can't tell, so the files are left alone.
+            Err(TryLockError::Error(_)) => true,
+        }
+    }
+
+    fn file(&self, document: &Document) -> PathBuf {
+        self.dir.join(format!(
+            "{}-{}.{}",
+            self.session,
+            document.id,
+            serialization::EXTENSION
+        ))
+    }
+
+    pub fn is_due(&mut self) -> bool {
+        if self.last.elapsed() < INTERVAL {
+            return false;
+        }
+
+        self.last = Instant::now();
+        true
+    }
+
+    pub fn save(&self, document: &mut Document) -> GameResult {
+        if !document.needs_autosave {
+            return Ok(());
+        }
+
+        // Undone back to the file, there is nothing to recover.
+        if !document.dirty() {
+            self.remove(document);
+            document.needs_autosave = false;
+            return Ok(());
+        }
+
+        std::fs::create_dir_all(&self.dir)?;
+
+        let mut contents = serialization::to_string(&document.level_data);
+        contents.push_str(&format!("{}{}\n", PATH_PREFIX, document.path));
+
+        serialization::write_atomic(&self.file(document), &contents)?;
+        document.needs_autosave = false;
+
+        Ok(())
+    }
+
+    pub fn remove(&self, document: &Document) {
+        let _ = std::fs::remove_file(self.file(document));
+    }
+
+    // Files of sessions that aren't running anymore. Lock files of those
+    // sessions are removed once nothing of theirs is left to restore.
+    pub fn recovered(&self) -> Vec<PathBuf> {
+        let Ok(entries) = std::fs::read_dir(&self.dir) else {
+            return Vec::new();
+        };
+
+        let paths = entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .collect::<Vec<_>>();
+
+        let owner = |path: &Path| {
+            let stem = path.file_stem()?.to_string_lossy().into_owned();
+            Some(stem.split('-').next()?.to_string())
+        };
+        let has_extension =
+            |path: &Path, name: &str| path.extension().is_some_and(|extension| extension == name);
+
+        let recovered = paths
+            .iter()
+            .filter(|path| has_extension(path, serialization::EXTENSION))
+            .filter(|path| owner(path).is_some_and(|session| !self.owner_alive(&session)))
+            .cloned()
+            .collect::<Vec<_>>();
+
+        for lock in paths.iter().filter(|path| has_extension(path, "lock")) {
+            let Some(session) = owner(lock) else {
+                continue;
+            };
+            let leftover = recovered
+                .iter()
+                .any(|path| owner(path) == Some(session.clone()));
+
+            if !leftover && !self.owner_alive(&session) {
+                let _ = std::fs::remove_file(lock);
+            }
+        }
+
+        recovered
+    }
+
+    pub fn restore(path: &Path) -> GameResult<Document> {
+        let contents = std::fs::read_to_string(path)?;
+
+        let mut document = Document::new();
+        document.level_data = serialization::from_str(&contents);
+        document.saved = None;
+        document.mark_changed();
+
+        if let Some(line) = contents.lines().find(|line| line.starts_with(PATH_PREFIX)) {
+            document.path = line[PATH_PREFIX.len()..].to_string();
+        }
+
+        Ok(document)
+    }
+
+    pub fn discard(paths: &[PathBuf]) {
+        for path in paths {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use ggez::glam::IVec2;
+
+    use super::*;
+    use crate::main_state::instances::{
+        floor::{Floor, FloorType},
+        wall::WallOrientation,
+        LayerData,
+    };
+
+    // An empty directory of its own for every test.
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "trapped_level_editor-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+
+        dir
+    }
+
+    fn changed_document() -> Document {
+        let mut document = Document::new();
+        document.path = String::from("levels/first");
+        document.level_data.insert(
+            IVec2::new(1, 2),
+            LayerData::Floor(Floor::default(FloorType::Normal)),
+            WallOrientation::Down,
+        );
+        document.saved = None;
+        document.mark_changed();
+
+        document
+    }
+
+    #[test]
+    fn keeps_the_files_of_running_sessions() {
+        let dir = test_dir("running");
+        let autosave = Autosave::new(dir.clone());
+        autosave.save(&mut changed_document()).unwrap();
+
+        // Another editor, locked like this one.
+        let other = File::create(dir.join("1_2.lock")).unwrap();
+        other.try_lock().unwrap();
+        fs::write(dir.join("1_2-0.lvl"), "").unwrap();
+
+        assert_eq!(autosave.recovered(), Vec::<PathBuf>::new());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn recovers_the_files_of_crashed_sessions() {
+        let dir = test_dir("crashed");
+
+        let crashed = Autosave::new(dir.clone());
+        let mut document = changed_document();
+        crashed.save(&mut document).unwrap();
+        let file = crashed.file(&document);
+        // The lock goes away with the crashed editor.
+        drop(crashed);
+
+        let autosave = Autosave::new(dir.clone());
+        assert_eq!(autosave.recovered(), std::slice::from_ref(&file));
+
+        let restored = Autosave::restore(&file).unwrap();
+        assert!(restored.level_data.floors.contains_key(&IVec2::new(1, 2)));
+        assert_eq!(restored.path, "levels/first");
+        assert!(restored.dirty());
+
+        // Once its files are gone, the lock of the crashed session is cleaned up too.
+        Autosave::discard(&[file]);
+        assert_eq!(autosave.recovered(), Vec::<PathBuf>::new());
+        let locks = fs::read_dir(&dir).unwrap().count();
+        assert_eq!(locks, 1);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn failed_writes_leave_no_temporary_file() {
+        let dir = test_dir("failed");
+        // Renaming a file over a directory fails after the temporary file was written.
+        fs::create_dir(dir.join("level")).unwrap();
+
+        assert!(serialization::write_atomic(&dir.join("level"), "").is_err());
+        assert!(!dir.join(".level.tmp").exists());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,219 @@
+use std::collections::{HashMap, HashSet};
+
+use ggez::glam::IVec2;
+
+use super::{
+    instances::{
+        floor::{Floor, FloorType},
+        wall::{Wall, WallData},
+        ActivatingColor, COLOR_NAMES,
+    },
+    level_data::LevelData,
+};
+
+// Changes to everything of a kind at once, typed into the command line.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BulkEdit {
+    // Objects, floors, walls and collectibles of the first color get the second one.
+    Recolor(ActivatingColor, ActivatingColor),
+    // Normal floors get the durability.
+    Durability(i32),
+    // Walls of the color get opened or closed.
+    Open(ActivatingColor, bool),
+    // Walls of the color start or stop depending on buttons.
+    InputDependent(ActivatingColor, bool),
+}
+
+impl BulkEdit {
+    pub fn parse(command: &str) -> Result<BulkEdit, String> {
+        let words = command.split_whitespace().collect::<Vec<_>>();
+
+        match words[..] {
+            ["recolor", from, to] => Ok(BulkEdit::Recolor(color(from)?, color(to)?)),
+            ["durability", "i"] => Ok(BulkEdit::Durability(Floor::INDESTRUCTIBLE)),
+            ["durability", durability] => durability
+                .parse()
+                .ok()
+                .filter(|durability| *durability > 0)
+                .map(BulkEdit::Durability)
+                .ok_or(format!(
+                    "durability must be a positive number or i, not `{}`",
+                    durability
+                )),
+            ["open", name] => Ok(BulkEdit::Open(color(name)?, true)),
+            ["close", name] => Ok(BulkEdit::Open(color(name)?, false)),
+            ["inputbased", name] | ["inputbased", name, "true"] => {
+                Ok(BulkEdit::InputDependent(color(name)?, true))
+            }
+            ["inputbased", name, "false"] => Ok(BulkEdit::InputDependent(color(name)?, false)),
+            _ => Err(format!("unknown command `{}`", command.trim())),
+        }
+    }
+
+    // The changed cells of `level_data`, only those in `region` if there is one.
+    // Pasting them back over the level applies the edit.
+    pub fn apply(&self, level_data: &LevelData, region: Option<&HashSet<IVec2>>) -> LevelData {
+        let inside = |pos: &IVec2| region.is_none_or(|region| region.contains(pos));
+
+        let mut changed = LevelData::new();
+
+        match *self {
+            BulkEdit::Recolor(from, to) => {
+                let recolor = |color: &mut ActivatingColor| {
+                    if *color == from {
+                        *color = to;
+                    }
+                };
+
+                changed.objects = edited(&level_data.objects, inside, |object| {
+                    recolor(&mut object.color)
+                });
+                changed.floors = edited(&level_data.floors, inside, |floor| {
+                    recolor(&mut floor.color)
+                });
+                changed.walls = edited(&level_data.walls, inside, |wall| {
+                    for wall_data in [&mut wall.right, &mut wall.down].into_iter().flatten() {
+                        recolor(&mut wall_data.color);
+                    }
+                });
+                changed.collectibles = edited(&level_data.collectibles, inside, |collectible| {
+                    recolor(&mut collectible.color)
+                });
+            }
+            BulkEdit::Durability(durability) => {
+                changed.floors = edited(&level_data.floors, inside, |floor| {
+                    if floor.floor_type == FloorType::Normal {
+                        floor.durability = durability;
+                    }
+                });
+            }
+            BulkEdit::Open(color, opened) => {
+                changed.walls = edit_walls(level_data, inside, color, |wall_data| {
+                    wall_data.opened = opened
+                });
+            }
+            BulkEdit::InputDependent(color, input_dependent) => {
+                changed.walls = edit_walls(level_data, inside, color, |wall_data| {
+                    wall_data.input_dependent = input_dependent
+                });
+            }
+        }
+
+        changed
+    }
+}
+
+fn color(name: &str) -> Result<ActivatingColor, String> {
+    COLOR_NAMES
+        .iter()
+        .find(|(_, color_name)| *color_name == name)
+        .map(|(color, _)| *color)
+        .ok_or(format!("unknown color `{}`", name))
+}
+
+// Copies of the contents in the region that `edit` changed.
+fn edited<T: Clone + PartialEq>(
+    contents: &HashMap<IVec2, T>,
+    inside: impl Fn(&IVec2) -> bool,
+    edit: impl Fn(&mut T),
+) -> HashMap<IVec2, T> {
+    contents
+        .iter()
+        .filter(|(pos, _)| inside(pos))
+        .filter_map(|(pos, content)| {
+            let mut content_edited = content.clone();
+            edit(&mut content_edited);
+
+            match content_edited == *content {
+                true => None,
+                false => Some((*pos, content_edited)),
+            }
+        })
+        .collect()
+}
+
+fn edit_walls(
+    level_data: &LevelData,
+    inside: impl Fn(&IVec2) -> bool,
+    color: ActivatingColor,
+    edit: impl Fn(&mut WallData),
+) -> HashMap<IVec2, Wall> {
+    edited(&level_data.walls, inside, |wall| {
+        for wall_data in [&mut wall.right, &mut wall.down].into_iter().flatten() {
+            if wall_data.color == color {
+                edit(wall_data);
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::main_state::serialization;
+
+    #[test]
+    fn parses_commands() {
+        assert_eq!(
+            BulkEdit::parse("recolor red blue"),
+            Ok(BulkEdit::Recolor(
+                ActivatingColor::Red,
+                ActivatingColor::Blue
+            ))
+        );
+        assert_eq!(
+            BulkEdit::parse(" durability  i "),
+            Ok(BulkEdit::Durability(Floor::INDESTRUCTIBLE))
+        );
+        assert_eq!(
+            BulkEdit::parse("close green"),
+            Ok(BulkEdit::Open(ActivatingColor::Green, false))
+        );
+        assert_eq!(
+            BulkEdit::parse("inputbased red"),
+            Ok(BulkEdit::InputDependent(ActivatingColor::Red, true))
+        );
+    }
+
+    #[test]
+    fn rejects_bad_commands() {
+        assert_eq!(
+            BulkEdit::parse("recolor red purple"),
+            Err(String::from("unknown color `purple`"))
+        );
+        assert!(BulkEdit::parse("durability 0").is_err());
+        assert!(BulkEdit::parse("durability").is_err());
+        assert!(BulkEdit::parse("paint red").is_err());
+    }
+
+    #[test]
+    fn returns_only_changed_cells() {
+        let level_data = serialization::from_str(
+            "3,1\nfloor 0,0  dur:-1 c:r\nfloor 1,0  dur:-1 c:b\nbutton 2,0  dur:-1 c:r\n",
+        );
+
+        let changed = BulkEdit::Recolor(ActivatingColor::Red, ActivatingColor::Green)
+            .apply(&level_data, None);
+
+        assert_eq!(changed.floors.len(), 2);
+        assert!(changed
+            .floors
+            .values()
+            .all(|floor| floor.color == ActivatingColor::Green));
+    }
+
+    #[test]
+    fn changes_only_the_region() {
+        let level_data = serialization::from_str(
+            "2,1\nfloor 0,0  dur:1 c:n\nfloor 1,0  dur:1 c:n\nfloor 2,0  dur:1 c:n\n",
+        );
+        let region = HashSet::from([IVec2::new(0, 0), IVec2::new(2, 0)]);
+
+        let changed = BulkEdit::Durability(3).apply(&level_data, Some(&region));
+
+        assert_eq!(
+            changed.floors.keys().copied().collect::<HashSet<_>>(),
+            region
+        );
+    }
+}
@@ -0,0 +1,60 @@
+use std::{fs, io::Read, path::Path};
+
+use ggez::Context;
+
+// Reads a toml config like `/palette.toml` from the resources or user config directory.
+// A missing file is not a problem, anything else is added to `problems` instead of
+// failing, so a broken config never prevents the editor from starting.
+pub fn load(ctx: &Context, path: &str, problems: &mut Vec<String>) -> Option<toml::Value> {
+    if !ctx.fs.exists(path) {
+        return None;
+    }
+
+    let mut contents = String::new();
+    let read = ctx
+        .fs
+        .open(path)
+        .map_err(|error| error.to_string())
+        .and_then(|mut file| {
+            file.read_to_string(&mut contents)
+                .map_err(|error| error.to_string())
+        });
+
+    match read {
+        Ok(_) => parse(path, &contents, problems),
+        Err(error) => {
+            problems.push(format!("{}: {}", path, error));
+            None
+        }
+    }
+}
+
+// The same for the command line, which has no context.
+pub fn load_file(
+    resources_dir: &Path,
+    path: &str,
+    problems: &mut Vec<String>,
+) -> Option<toml::Value> {
+    let file = resources_dir.join(path.trim_start_matches('/'));
+    if !file.is_file() {
+        return None;
+    }
+
+    match fs::read_to_string(&file) {
+        Ok(contents) => parse(path, &contents, problems),
+        Err(error) => {
+            problems.push(format!("{}: {}", path, error));
+            None
+        }
+    }
+}
+
+pub fn parse(path: &str, contents: &str, problems: &mut Vec<String>) -> Option<toml::Value> {
+    match contents.parse::<toml::Value>() {
+        Ok(config) => Some(config),
+        Err(error) => {
+            problems.push(format!("{}: {}", path, error));
+            None
+        }
+    }
+}
@@ -0,0 +1,270 @@
+use std::{
+    collections::HashSet,
+    fs,
+    path::Path,
+    sync::atomic::{AtomicU64, Ordering},
+    time::SystemTime,
+};
+
+use ggez::{glam::IVec2, graphics::Rect};
+
+use super::{
+    game::Move,
+    history::History,
+    instances::{Layer, LayerSlot},
+    level_data::LevelData,
+    replay,
+};
+
+static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Selection {
+    // Corners in the order they were dragged.
+    Rect(IVec2, IVec2),
+    // Single cells, like the matches of a find.
+    Cells(HashSet<IVec2>),
+}
+
+// One open level together with everything that belongs to it and not to the editor.
+pub struct Document {
+    pub id: u64,
+    pub level_data: LevelData,
+    // Goes up with every change of `level_data`, so what is computed from the
+    // level can tell when it is outdated without comparing whole levels.
+    pub revision: u64,
+    pub history: History,
+    pub screen_rect: Rect,
+    pub path: String,
+    // Shown instead of `untitled` while there is no path, like the seed of a generated level.
+    pub title: Option<String>,
+    // The history state the level was last loaded or saved in. None for levels
+    // that never were, like restored or generated ones.
+    pub saved: Option<u64>,
+    pub needs_autosave: bool,
+    pub selection: Option<Selection>,
+    // Modification time of the file when it was last loaded or saved.
+    pub modified: Option<SystemTime>,
+    // The replay saved next to the level file and whether it still solves the level.
+    pub replay: Option<Vec<Move>>,
+    pub replay_check: Option<Result<usize, String>>,
+    // Whether the level changed since the replay was last checked.
+    replay_outdated: bool,
+}
+
+impl Document {
+    pub fn new() -> Document {
+        Document {
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+            level_data: LevelData::new(),
+            revision: 0,
+            history: History::new(),
+            screen_rect: Rect::new(0.0, 0.0, 128.0, 96.0),
+            path: String::new(),
+            title: None,
+            saved: Some(0),
+            needs_autosave: false,
+            selection: None,
+            modified: None,
+            replay: None,
+            replay_check: None,
+            replay_outdated: false,
+        }
+    }
+
+    pub fn name(&self) -> String {
+        match Path::new(&self.path).file_name() {
+            Some(name) => name.to_string_lossy().into_owned(),
+            None => self
+                .title
+                .clone()
+                .unwrap_or_else(|| String::from("untitled")),
+        }
+    }
+
+    // Whether the level differs from the file, undoing back to it counts as no change.
+    pub fn dirty(&self) -> bool {
+        self.saved.is_none() || self.history.state() != self.saved
+    }
+
+    pub fn mark_saved(&mut self) {
+        self.history.commit();
+        self.saved = self.history.state();
+        self.needs_autosave = false;
+    }
+
+    pub fn file_modified(&self) -> Option<SystemTime> {
+        fs::metadata(&self.path).ok()?.modified().ok()
+    }
+
+    // Whether the file was changed by someone else since it was last loaded or saved.
+    pub fn changed_on_disk(&self) -> bool {
+        self.modified.is_some() && self.file_modified() != self.modified
+    }
+
+    pub fn edit(
+        &mut self,
+        pos: IVec2,
+        layer: Layer<(), (), (), ()>,
+        edit: impl FnOnce(&mut LevelData),
+    ) {
+        if self.history.edit(&mut self.level_data, pos, layer, edit) {
+            self.mark_changed();
+        }
+    }
+
+    pub fn mark_changed(&mut self) {
+        self.revision += 1;
+        self.needs_autosave = true;
+        self.replay_outdated = true;
+    }
+
+    pub fn load_replay(&mut self) {
+        let path = replay::path(Path::new(&self.path));
+
+        match path.is_file() {
+            true => match replay::load(&path) {
+                Ok(moves) => self.replay = Some(moves),
+                Err(error) => {
+                    self.replay = None;
+                    self.replay_check = Some(Err(error));
+                    return;
+                }
+            },
+            false => self.replay = None,
+        }
+
+        self.check_replay();
+    }
+
+    // Replays are short, so they are simply played again after every committed change.
+    pub fn check_replay(&mut self) {
+        self.replay_outdated = false;
+        self.replay_check = self
+            .replay
+            .as_ref()
+            .map(|moves| replay::check(&self.level_data, moves));
+    }
+
+    pub fn commit(&mut self) {
+        self.history.commit();
+
+        if self.replay_outdated {
+            self.check_replay();
+        }
+    }
+
+    // Layer locks don't apply to undo and redo: a step always changes every layer
+    // it touched, otherwise the steps around it would no longer fit the level.
+    pub fn undo(&mut self) {
+        if self.history.undo(&mut self.level_data) {
+            self.mark_changed();
+            self.commit();
+        }
+    }
+
+    pub fn redo(&mut self) {
+        if self.history.redo(&mut self.level_data) {
+            self.mark_changed();
+            self.commit();
+        }
+    }
+
+    // The rectangle around the selection, with its corners ordered as (min, max).
+    pub fn selection_rect(&self) -> Option<(IVec2, IVec2)> {
+        match self.selection.as_ref()? {
+            Selection::Rect(start, end) => Some((start.min(*end), start.max(*end))),
+            Selection::Cells(cells) => {
+                let first = *cells.iter().next()?;
+
+                Some(cells.iter().fold((first, first), |(min, max), pos| {
+                    (min.min(*pos), max.max(*pos))
+                }))
+            }
+        }
+    }
+
+    // The selected cells that have anything on them.
+    pub fn selected_cells(&self) -> Option<HashSet<IVec2>> {
+        let (min, max) = self.selection_rect()?;
+
+        Some(
+            self.level_data
+                .positions()
+                .filter(|pos| pos.cmpge(min).all() && pos.cmple(max).all())
+                .filter(|pos| match &self.selection {
+                    Some(Selection::Cells(cells)) => cells.contains(pos),
+                    _ => true,
+                })
+                .copied()
+                .collect(),
+        )
+    }
+
+    pub fn copy(&self) -> Option<LevelData> {
+        let (min, max) = self.selection_rect()?;
+        let mut copied = self.level_data.copy_region(min, max);
+
+        if let Some(Selection::Cells(cells)) = &self.selection {
+            copied.retain(|pos| cells.contains(&(*pos + min)));
+        }
+
+        Some(copied)
+    }
+
+    // Removes the cells of `region`, a copy of the selection, as one undoable step.
+    pub fn erase(&mut self, region: &LevelData) {
+        let Some((min, _)) = self.selection_rect() else {
+            return;
+        };
+
+        for pos in region.objects.keys() {
+            self.edit(*pos + min, Layer::Object(()), |level_data| {
+                level_data.set_slot(*pos + min, LayerSlot::Object(None))
+            });
+        }
+        for pos in region.floors.keys() {
+            self.edit(*pos + min, Layer::Floor(()), |level_data| {
+                level_data.set_slot(*pos + min, LayerSlot::Floor(None))
+            });
+        }
+        for pos in region.walls.keys() {
+            self.edit(*pos + min, Layer::Wall(()), |level_data| {
+                level_data.set_slot(*pos + min, LayerSlot::Wall(None))
+            });
+        }
+        for pos in region.collectibles.keys() {
+            self.edit(*pos + min, Layer::Collectible(()), |level_data| {
+                level_data.set_slot(*pos + min, LayerSlot::Collectible(None))
+            });
+        }
+
+        self.commit();
+    }
+
+    // Pasted cells replace whatever was on the same layer, as one undoable step.
+    pub fn paste(&mut self, clipboard: &LevelData, at: IVec2) {
+        for (pos, object) in &clipboard.objects {
+            self.edit(*pos + at, Layer::Object(()), |level_data| {
+                level_data.set_slot(*pos + at, LayerSlot::Object(Some(object.clone())))
+            });
+        }
+        for (pos, floor) in &clipboard.floors {
+            self.edit(*pos + at, Layer::Floor(()), |level_data| {
+                level_data.set_slot(*pos + at, LayerSlot::Floor(Some(floor.clone())))
+            });
+        }
+        for (pos, wall) in &clipboard.walls {
+            self.edit(*pos + at, Layer::Wall(()), |level_data| {
+                level_data.set_slot(*pos + at, LayerSlot::Wall(Some(wall.clone())))
+            });
+        }
+        for (pos, collectible) in &clipboard.collectibles {
+            self.edit(*pos + at, Layer::Collectible(()), |level_data| {
+                level_data.set_slot(*pos + at, LayerSlot::Collectible(Some(collectible.clone())))
+            });
+        }
+
+        self.commit();
+    }
+}
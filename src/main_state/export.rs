@@ -0,0 +1,254 @@
+use std::{collections::HashMap, fs, path::Path};
+
+use ggez::{glam::IVec2, graphics::Color, GameError, GameResult};
+use image::{Pixel, Rgba, RgbaImage};
+
+use super::{
+    instances::{wall::WallOrientation, ActivatingColor, LayerContent},
+    level_data::LevelData,
+    resources::{DrawId, Resources},
+    serialization,
+    sprite_manifest::{Sprite, SpriteManifest, MANIFEST_PATH},
+    MainState,
+};
+
+// Draws levels into PNG files on the CPU, so it works without a window or a GPU.
+// Sprites, layer order and tinting follow `LevelData::draw_with` and
+// `Resources::draw_content`, except that input dependent walls are drawn in
+// their initial state and durability labels are left out.
+pub struct Renderer {
+    images: HashMap<String, RgbaImage>,
+    sprites: HashMap<DrawId, Sprite>,
+}
+
+impl Renderer {
+    // Problems are returned as messages, missing sprites get flat placeholders.
+    pub fn new(resources_dir: &Path) -> (Renderer, Vec<String>) {
+        let mut renderer = Renderer {
+            images: HashMap::new(),
+            sprites: HashMap::new(),
+        };
+        let mut problems = Vec::new();
+
+        let manifest_path = resources_dir.join(MANIFEST_PATH.trim_start_matches('/'));
+        let manifest = fs::read_to_string(&manifest_path)
+            .map_err(|error| vec![error.to_string()])
+            .and_then(|contents| SpriteManifest::parse(&contents));
+
+        let manifest = match manifest {
+            Ok(manifest) => manifest,
+            Err(errors) => {
+                for error in errors {
+                    problems.push(format!("{}: {}", manifest_path.display(), error));
+                }
+                return (renderer, problems);
+            }
+        };
+
+        for (name, path) in manifest.images {
+            let path = resources_dir.join(path.trim_start_matches('/'));
+            match image::open(&path) {
+                Ok(image) => {
+                    renderer.images.insert(name, image.into_rgba8());
+                }
+                Err(error) => problems.push(format!("{}: {}", path.display(), error)),
+            }
+        }
+        renderer.sprites = manifest.sprites;
+
+        (renderer, problems)
+    }
+
+    pub fn render(&self, level_data: &LevelData) -> RgbaImage {
+        let size = MainState::CELL_SIZE;
+        let (min, max) = level_data.extent();
+        // One more cell to the right and below for the walls on the last edges.
+        let cells = max - min + IVec2::splat(2);
+
+        let mut image = RgbaImage::from_pixel(
+            (cells.x * size) as u32,
+            (cells.y * size) as u32,
+            Rgba([0, 0, 0, 255]),
+        );
+        let origin = |pos: &IVec2| (*pos - min) * size;
+
+        for (pos, floor) in &level_data.floors {
+            self.draw_cell(&mut image, origin(pos), &LayerContent::Floor(floor.clone()));
+        }
+        for (pos, collectible) in &level_data.collectibles {
+            let content = LayerContent::Collectible(collectible.clone());
+            self.draw_cell(&mut image, origin(pos), &content);
+        }
+        for (pos, object) in &level_data.objects {
+            self.draw_cell(
+                &mut image,
+                origin(pos),
+                &LayerContent::Object(object.clone()),
+            );
+        }
+        for (pos, wall) in &level_data.walls {
+            for (orientation, wall_data) in [
+                (WallOrientation::Right, &wall.right),
+                (WallOrientation::Down, &wall.down),
+            ] {
+                if let Some(wall_data) = wall_data {
+                    let draw_id = Resources::wall_draw_id(orientation, wall_data.opened);
+                    self.draw_sprite(&mut image, origin(pos), draw_id, wall_data.color);
+                }
+            }
+        }
+
+        image
+    }
+
+    fn draw_cell(&self, image: &mut RgbaImage, origin: IVec2, content: &LayerContent) {
+        if let Some((draw_id, color)) = Resources::cell_drawing(content) {
+            self.draw_sprite(image, origin, draw_id, color);
+        }
+    }
+
+    fn draw_sprite(
+        &self,
+        image: &mut RgbaImage,
+        origin: IVec2,
+        draw_id: DrawId,
+        color: ActivatingColor,
+    ) {
+        let tint: Color = color.into();
+        let sprite = self
+            .sprites
+            .get(&draw_id)
+            .and_then(|sprite| Some((self.images.get(&sprite.image)?, sprite.region)));
+
+        let Some((source, region)) = sprite else {
+            Self::draw_placeholder(image, origin, draw_id, tint);
+            return;
+        };
+
+        for y in 0..region.h as u32 {
+            for x in 0..region.w as u32 {
+                let Some(pixel) =
+                    source.get_pixel_checked(region.x as u32 + x, region.y as u32 + y)
+                else {
+                    continue;
+                };
+                Self::blend(image, origin + IVec2::new(x as i32, y as i32), *pixel, tint);
+            }
+        }
+    }
+
+    // Flat shapes in place of sprites that could not be loaded.
+    fn draw_placeholder(image: &mut RgbaImage, origin: IVec2, draw_id: DrawId, tint: Color) {
+        let (x, y, w, h) = match draw_id {
+            DrawId::Floor | DrawId::Floor2 | DrawId::Floor3 => (0, 0, 16, 16),
+            DrawId::VerticalWallOpened | DrawId::VerticalWallClosed => (15, 0, 2, 16),
+            DrawId::HorizontalWallOpened | DrawId::HorizontalWallClosed => (0, 15, 16, 2),
+            _ => (3, 3, 10, 10),
+        };
+        let shade = match draw_id {
+            DrawId::Floor => 80,
+            DrawId::Floor2 => 115,
+            DrawId::Floor3 => 150,
+            _ => 255,
+        };
+
+        for dy in y..y + h {
+            for dx in x..x + w {
+                let pixel = Rgba([shade, shade, shade, 255]);
+                Self::blend(image, origin + IVec2::new(dx, dy), pixel, tint);
+            }
+        }
+    }
+
+    fn blend(image: &mut RgbaImage, at: IVec2, pixel: Rgba<u8>, tint: Color) {
+        if at.x < 0 || at.y < 0 || at.x as u32 >= image.width() || at.y as u32 >= image.height() {
+            return;
+        }
+
+        let tint = [tint.r, tint.g, tint.b, tint.a];
+        let mut tinted = pixel;
+        for (channel, tint) in tinted.0.iter_mut().zip(tint) {
+            *channel = (*channel as f32 * tint).round() as u8;
+        }
+
+        image.get_pixel_mut(at.x as u32, at.y as u32).blend(&tinted);
+    }
+
+    pub fn export(&self, level: &Path, png: &Path) -> GameResult {
+        let contents = fs::read_to_string(level)
+            .map_err(|error| GameError::CustomError(format!("{}: {}", level.display(), error)))?;
+        let level_data = serialization::from_str(&contents);
+
+        self.render(&level_data)
+            .save(png)
+            .map_err(|error| GameError::CustomError(format!("{}: {}", png.display(), error)))
+    }
+
+    // Renders every level file in `dir` into `out_dir/<file name>.png`. Files that fail
+    // don't stop the others, all failures are reported together at the end.
+    pub fn export_directory(&self, dir: &Path, out_dir: &Path) -> GameResult {
+        fs::create_dir_all(out_dir)?;
+
+        let levels = serialization::level_files(dir)?;
+        let mut failures = Vec::new();
+
+        for level in levels {
+            let Some(name) = level.file_name() else {
+                continue;
+            };
+            let png = out_dir.join(format!("{}.png", name.to_string_lossy()));
+
+            match self.export(&level, &png) {
+                Ok(()) => println!("{} -> {}", level.display(), png.display()),
+                Err(GameError::CustomError(error)) => failures.push(error),
+                Err(error) => failures.push(error.to_string()),
+            }
+        }
+
+        match failures.is_empty() {
+            true => Ok(()),
+            false => Err(GameError::CustomError(failures.join("\n"))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LEVEL: &str = include_str!("../../tests/fixtures/button_wall");
+
+    #[test]
+    fn renders_with_the_shipped_sprites() {
+        let resources = Path::new(env!("CARGO_MANIFEST_DIR")).join("resources");
+        let (renderer, problems) = Renderer::new(&resources);
+        assert_eq!(problems, Vec::<String>::new());
+
+        let image = renderer.render(&serialization::from_str(LEVEL));
+
+        // Five by three cells and one more for the walls on the last edges.
+        assert_eq!(image.dimensions(), (6 * 16, 4 * 16));
+        // Nothing was drawn in the cell under the last floor.
+        assert_eq!(
+            *image.get_pixel(4 * 16 + 8, 2 * 16 + 8),
+            Rgba([0, 0, 0, 255])
+        );
+        assert_ne!(*image.get_pixel(4 * 16 + 8, 8), Rgba([0, 0, 0, 255]));
+    }
+
+    #[test]
+    fn renders_placeholders_without_sprites() {
+        let (renderer, problems) = Renderer::new(Path::new("missing resources"));
+        assert_eq!(problems.len(), 1);
+
+        let image = renderer.render(&serialization::from_str(LEVEL));
+
+        assert_eq!(image.dimensions(), (6 * 16, 4 * 16));
+        // An indestructible floor with the player on top of it.
+        assert_eq!(*image.get_pixel(1, 1), Rgba([80, 80, 80, 255]));
+        assert_eq!(*image.get_pixel(8, 8), Rgba([255, 255, 255, 255]));
+        // The red button, without a floor below it.
+        assert_eq!(*image.get_pixel(16 + 8, 2 * 16 + 8), Rgba([255, 0, 0, 255]));
+        assert_eq!(*image.get_pixel(16 + 1, 2 * 16 + 1), Rgba([0, 0, 0, 255]));
+    }
+}
@@ -0,0 +1,362 @@
+use ggez::{
+    glam::IVec2,
+    graphics::{Canvas, Color, DrawMode, DrawParam, Mesh, MeshBuilder, Rect},
+    Context, GameResult,
+};
+
+use super::{
+    document::Document,
+    instances::{
+        collectible::CollectibleType, floor::FloorType, object::ObjectType, wall::WallType,
+        ActivatingColor, Layer, COLOR_NAMES,
+    },
+    layers::layer_name,
+    level_data::LevelData,
+    MainState,
+};
+
+const LAYERS: [Layer; 4] = [
+    Layer::Object(()),
+    Layer::Floor(()),
+    Layer::Wall(()),
+    Layer::Collectible(()),
+];
+
+// The names of the level file format.
+const TYPES: [&str; 8] = [
+    "player", "box", "telebox", "floor", "button", "teleport", "wall", "win",
+];
+
+#[derive(Debug, Clone, PartialEq)]
+enum Term {
+    Layer(Layer),
+    Type(&'static str),
+    Color(ActivatingColor),
+    InputDependent(bool),
+    Closed(bool),
+    Durability(i32),
+}
+
+// One thing in the level, described by what a query can ask about.
+struct Entity {
+    layer: Layer,
+    type_name: &'static str,
+    color: ActivatingColor,
+    input_dependent: Option<bool>,
+    closed: Option<bool>,
+    durability: Option<i32>,
+}
+
+impl Entity {
+    fn matches(&self, term: &Term) -> bool {
+        match term {
+            Term::Layer(layer) => self.layer == *layer,
+            Term::Type(type_name) => self.type_name == *type_name,
+            Term::Color(color) => self.color == *color,
+            Term::InputDependent(input_dependent) => self.input_dependent == Some(*input_dependent),
+            Term::Closed(closed) => self.closed == Some(*closed),
+            // Every negative durability means indestructible.
+            Term::Durability(durability) if *durability < 0 => {
+                self.durability.is_some_and(|durability| durability < 0)
+            }
+            Term::Durability(durability) => self.durability == Some(*durability),
+        }
+    }
+}
+
+// Terms like `type=button color=red` that all have to match. A bare word is a
+// layer or a type, so `wall inputbased=true` finds the walls that depend on buttons.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Query {
+    terms: Vec<Term>,
+}
+
+impl Query {
+    pub fn parse(query: &str) -> Result<Query, String> {
+        let mut terms = Vec::new();
+
+        for word in query.split_whitespace() {
+            let term = match word.split_once('=') {
+                None => layer(word)
+                    .map(Term::Layer)
+                    .or_else(|| type_name(word).map(Term::Type))
+                    .ok_or(format!("unknown layer or type `{}`", word))?,
+                Some(("layer", value)) => {
+                    Term::Layer(layer(value).ok_or(format!("unknown layer `{}`", value))?)
+                }
+                Some(("type", value)) => {
+                    Term::Type(type_name(value).ok_or(format!("unknown type `{}`", value))?)
+                }
+                Some(("color", value)) => Term::Color(
+                    COLOR_NAMES
+                        .iter()
+                        .find(|(_, name)| *name == value)
+                        .map(|(color, _)| *color)
+                        .ok_or(format!("unknown color `{}`", value))?,
+                ),
+                Some(("inputbased", value)) => Term::InputDependent(boolean(value)?),
+                Some(("closed", value)) => Term::Closed(boolean(value)?),
+                Some(("dur" | "durability", value)) => Term::Durability(match value {
+                    "i" => -1,
+                    _ => value.parse().map_err(|_| {
+                        format!("durability must be a number or i, not `{}`", value)
+                    })?,
+                }),
+                Some((key, _)) => return Err(format!("unknown property `{}`", key)),
+            };
+
+            terms.push(term);
+        }
+
+        match terms.is_empty() {
+            true => Err(String::from("nothing to find")),
+            false => Ok(Query { terms }),
+        }
+    }
+
+    // Cells with a match, in reading order.
+    pub fn matches(&self, level_data: &LevelData) -> Vec<IVec2> {
+        let mut matches = entities(level_data)
+            .filter(|(_, entity)| self.terms.iter().all(|term| entity.matches(term)))
+            .map(|(pos, _)| pos)
+            .collect::<Vec<_>>();
+
+        matches.sort_by_key(|pos| (pos.y, pos.x));
+        matches.dedup();
+
+        matches
+    }
+}
+
+fn layer(name: &str) -> Option<Layer> {
+    LAYERS.into_iter().find(|layer| layer_name(*layer) == name)
+}
+
+fn type_name(name: &str) -> Option<&'static str> {
+    TYPES.into_iter().find(|type_name| *type_name == name)
+}
+
+fn boolean(value: &str) -> Result<bool, String> {
+    match value {
+        "true" => Ok(true),
+        "false" => Ok(false),
+        _ => Err(format!("`{}` must be true or false", value)),
+    }
+}
+
+fn entities(level_data: &LevelData) -> impl Iterator<Item = (IVec2, Entity)> + '_ {
+    let objects = level_data.objects.iter().map(|(pos, object)| {
+        let type_name = match object.object_type {
+            ObjectType::Player => "player",
+            ObjectType::Box => "box",
+            ObjectType::TeleBox => "telebox",
+        };
+        (*pos, entity(Layer::Object(()), type_name, object.color))
+    });
+
+    let floors = level_data.floors.iter().map(|(pos, floor)| {
+        let type_name = match floor.floor_type {
+            FloorType::Normal => "floor",
+            FloorType::Button => "button",
+            FloorType::Teleport => "teleport",
+        };
+        let mut entity = entity(Layer::Floor(()), type_name, floor.color);
+        entity.durability = Some(floor.durability);
+        (*pos, entity)
+    });
+
+    let walls = level_data.walls.iter().flat_map(|(pos, wall)| {
+        [&wall.right, &wall.down]
+            .into_iter()
+            .flatten()
+            .map(|wall_data| {
+                let type_name = match wall_data.wall_type {
+                    WallType::Normal => "wall",
+                };
+                let mut entity = entity(Layer::Wall(()), type_name, wall_data.color);
+                entity.input_dependent = Some(wall_data.input_dependent);
+                entity.closed = Some(!wall_data.opened);
+                (*pos, entity)
+            })
+    });
+
+    let collectibles = level_data.collectibles.iter().map(|(pos, collectible)| {
+        let type_name = match collectible.collectible_type {
+            CollectibleType::Win => "win",
+        };
+        (
+            *pos,
+            entity(Layer::Collectible(()), type_name, collectible.color),
+        )
+    });
+
+    objects.chain(floors).chain(walls).chain(collectibles)
+}
+
+fn entity(layer: Layer, type_name: &'static str, color: ActivatingColor) -> Entity {
+    Entity {
+        layer,
+        type_name,
+        color,
+        input_dependent: None,
+        closed: None,
+        durability: None,
+    }
+}
+
+// The last query, with the match the camera was last moved to. Matches are looked
+// up again whenever the level has changed, so they follow edits.
+#[derive(Debug, Default)]
+pub struct Finder {
+    query: Option<Query>,
+    current: usize,
+    // The matches with the document id and revision they were looked up for.
+    matches: Option<((u64, u64), Vec<IVec2>)>,
+}
+
+impl Finder {
+    pub fn new() -> Finder {
+        Finder::default()
+    }
+
+    pub fn find(&mut self, query: Query) {
+        self.query = Some(query);
+        self.current = 0;
+        self.matches = None;
+    }
+
+    pub fn clear(&mut self) {
+        self.query = None;
+        self.matches = None;
+    }
+
+    pub fn matches(&mut self, document: &Document) -> &[IVec2] {
+        let key = (document.id, document.revision);
+        if self.matches.as_ref().is_none_or(|(found, _)| *found != key) {
+            let matches = self
+                .query
+                .as_ref()
+                .map_or(Vec::new(), |query| query.matches(&document.level_data));
+            self.matches = Some((key, matches));
+        }
+
+        self.matches.as_ref().map_or(&[], |(_, matches)| matches)
+    }
+
+    // Steps to the next or previous match and returns it.
+    pub fn step(&mut self, document: &Document, delta: i32) -> Option<IVec2> {
+        let count = self.matches(document).len();
+        if count == 0 {
+            return None;
+        }
+
+        let current = (self.current as i32 + delta).rem_euclid(count as i32) as usize;
+        self.current = current;
+        Some(self.matches(document)[current])
+    }
+
+    pub fn current(&mut self, document: &Document) -> Option<IVec2> {
+        let current = self.current;
+        let matches = self.matches(document);
+
+        matches
+            .get(current.min(matches.len().saturating_sub(1)))
+            .copied()
+    }
+
+    // Like `find: 2/5`, for the status bar.
+    pub fn status(&mut self, document: &Document) -> Option<String> {
+        self.query.as_ref()?;
+
+        let current = self.current;
+        let matches = self.matches(document);
+        Some(match matches.is_empty() {
+            true => String::from("find: nothing"),
+            false => format!(
+                "find: {}/{}",
+                current.min(matches.len() - 1) + 1,
+                matches.len()
+            ),
+        })
+    }
+
+    // Outlines the matches in world coordinates, the current one brighter.
+    pub fn draw(&mut self, ctx: &Context, canvas: &mut Canvas, document: &Document) -> GameResult {
+        let current = self.current(document);
+        let matches = self.matches(document);
+        if matches.is_empty() {
+            return Ok(());
+        }
+
+        let size = MainState::CELL_SIZE as f32;
+        let mut builder = MeshBuilder::new();
+
+        for pos in matches {
+            let rect = Rect::new(pos.x as f32 * size, pos.y as f32 * size, size, size);
+            let color = match Some(*pos) == current {
+                true => Color::WHITE,
+                false => Color::new(1.0, 0.8, 0.0, 1.0),
+            };
+            builder.rectangle(DrawMode::stroke(2.0), rect, color)?;
+        }
+
+        let mesh = Mesh::from_data(ctx, builder.build());
+        canvas.draw(&mesh, DrawParam::default());
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::main_state::serialization;
+
+    #[test]
+    fn parses_terms() {
+        let query = Query::parse("wall color=red inputbased=true").unwrap();
+
+        assert_eq!(
+            query.terms,
+            [
+                Term::Layer(Layer::Wall(())),
+                Term::Color(ActivatingColor::Red),
+                Term::InputDependent(true),
+            ]
+        );
+        assert_eq!(
+            Query::parse("button dur=i").unwrap().terms,
+            [Term::Type("button"), Term::Durability(-1)]
+        );
+    }
+
+    #[test]
+    fn rejects_bad_terms() {
+        assert_eq!(Query::parse("  "), Err(String::from("nothing to find")));
+        assert_eq!(
+            Query::parse("lava"),
+            Err(String::from("unknown layer or type `lava`"))
+        );
+        assert!(Query::parse("closed=maybe").is_err());
+        assert!(Query::parse("size=2").is_err());
+    }
+
+    #[test]
+    fn matches_in_reading_order() {
+        let level_data = serialization::from_str(
+            "2,2\nfloor 1,1  dur:2 c:n\nfloor 0,1  dur:-3 c:n\nbutton 1,0  dur:-1 c:r\n\
+             floor 0,0  dur:-1 c:n\n",
+        );
+
+        assert_eq!(
+            Query::parse("dur=i").unwrap().matches(&level_data),
+            [IVec2::new(0, 0), IVec2::new(1, 0), IVec2::new(0, 1)]
+        );
+        assert_eq!(
+            Query::parse("type=floor dur=2")
+                .unwrap()
+                .matches(&level_data),
+            [IVec2::new(1, 1)]
+        );
+    }
+}
@@ -0,0 +1,451 @@
+use std::collections::HashMap;
+
+use ggez::glam::IVec2;
+
+use super::{
+    instances::{
+        collectible::CollectibleType,
+        floor::FloorType,
+        object::{Object, ObjectType},
+        wall::WallData,
+        ActivatingColor,
+    },
+    level_data::LevelData,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Move {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+impl Move {
+    pub const ALL: [Move; 4] = [Move::Up, Move::Down, Move::Left, Move::Right];
+
+    pub fn offset(self) -> IVec2 {
+        match self {
+            Move::Up => IVec2::NEG_Y,
+            Move::Down => IVec2::Y,
+            Move::Left => IVec2::NEG_X,
+            Move::Right => IVec2::X,
+        }
+    }
+
+    pub fn letter(self) -> char {
+        match self {
+            Move::Up => 'U',
+            Move::Down => 'D',
+            Move::Left => 'L',
+            Move::Right => 'R',
+        }
+    }
+
+    pub fn from_letter(letter: char) -> Option<Move> {
+        Move::ALL
+            .into_iter()
+            .find(|m| m.letter() == letter.to_ascii_uppercase())
+    }
+}
+
+// Everything that changes while playing. Boxes, fragile floors and wins are
+// stored in the order of the matching lists in `Game`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct State {
+    pub player: IVec2,
+    pub boxes: Vec<IVec2>,
+    pub durabilities: Vec<i32>,
+    pub collected: Vec<bool>,
+}
+
+// The rules of the game, as far as the editor needs them to play levels, check
+// replays and search for solutions:
+// - the player moves by one cell and can only stand on floors,
+// - closed walls block the edge they are on, input dependent walls switch
+//   between opened and closed while a button of their color is pressed,
+// - buttons are pressed by the player or any box standing on them,
+// - the player pushes boxes, one at a time, onto floors,
+// - teleports carry the player and teleboxes to the next teleport of the same color,
+// - normal floors with a durability lose one each time the player leaves them
+//   and break at zero,
+// - the level is solved once every win is collected.
+#[derive(Debug, Clone)]
+pub struct Game {
+    level_data: LevelData,
+    teleports: HashMap<IVec2, IVec2>,
+    player_kind: Object,
+    box_kinds: Vec<Object>,
+    fragile: Vec<IVec2>,
+    wins: Vec<IVec2>,
+    start: State,
+}
+
+impl Game {
+    pub fn new(level_data: &LevelData) -> Result<Game, String> {
+        let players = sorted(
+            level_data
+                .objects
+                .iter()
+                .filter(|(_, object)| object.object_type == ObjectType::Player)
+                .map(|(pos, _)| *pos),
+        );
+        let player = match players[..] {
+            [player] => player,
+            [] => return Err(String::from("the level has no player")),
+            _ => return Err(format!("the level has {} players", players.len())),
+        };
+
+        let boxes = sorted(
+            level_data
+                .objects
+                .iter()
+                .filter(|(_, object)| object.object_type != ObjectType::Player)
+                .map(|(pos, _)| *pos),
+        );
+        let box_kinds = boxes
+            .iter()
+            .map(|pos| level_data.objects[pos].clone())
+            .collect();
+
+        let fragile = sorted(
+            level_data
+                .floors
+                .iter()
+                .filter(|(_, floor)| floor.floor_type == FloorType::Normal)
+                .filter(|(_, floor)| !floor.is_indestructible())
+                .map(|(pos, _)| *pos),
+        );
+        let durabilities = fragile
+            .iter()
+            .map(|pos| level_data.floors[pos].durability)
+            .collect();
+
+        let wins = sorted(
+            level_data
+                .collectibles
+                .iter()
+                .filter(|(_, collectible)| collectible.collectible_type == CollectibleType::Win)
+                .map(|(pos, _)| *pos),
+        );
+
+        let start = State {
+            player,
+            boxes,
+            durabilities,
+            collected: vec![false; wins.len()],
+        };
+
+        Ok(Game {
+            level_data: level_data.clone(),
+            teleports: Self::pair_teleports(level_data),
+            player_kind: level_data.objects[&player].clone(),
+            box_kinds,
+            fragile,
+            wins,
+            start,
+        })
+    }
+
+    // Every teleport leads to the next one of its color, in reading order.
+    pub fn pair_teleports(level_data: &LevelData) -> HashMap<IVec2, IVec2> {
+        let mut by_color: HashMap<ActivatingColor, Vec<IVec2>> = HashMap::new();
+        for (pos, floor) in &level_data.floors {
+            if floor.floor_type == FloorType::Teleport {
+                by_color.entry(floor.color).or_default().push(*pos);
+            }
+        }
+
+        let mut teleports = HashMap::new();
+        for positions in by_color.into_values() {
+            let positions = sorted(positions.into_iter());
+            if positions.len() < 2 {
+                continue;
+            }
+            for (i, pos) in positions.iter().enumerate() {
+                teleports.insert(*pos, positions[(i + 1) % positions.len()]);
+            }
+        }
+
+        teleports
+    }
+
+    pub fn start(&self) -> &State {
+        &self.start
+    }
+
+    pub fn solved(&self, state: &State) -> bool {
+        !state.collected.is_empty() && state.collected.iter().all(|collected| *collected)
+    }
+
+    // The state after `m`, or `None` if the move is blocked and nothing would change.
+    pub fn step(&self, state: &State, m: Move) -> Option<State> {
+        let offset = m.offset();
+        let target = state.player + offset;
+
+        if self.blocked(state, state.player, m) || !self.has_floor(state, target) {
+            return None;
+        }
+
+        let mut next = state.clone();
+
+        if let Some(index) = state.boxes.iter().position(|pos| *pos == target) {
+            let box_target = target + offset;
+            if self.blocked(state, target, m)
+                || !self.has_floor(state, box_target)
+                || state.boxes.contains(&box_target)
+            {
+                return None;
+            }
+
+            next.boxes[index] = box_target;
+            if self.box_kinds[index].object_type == ObjectType::TeleBox {
+                if let Some(exit) = self.teleport(&next, box_target) {
+                    if exit != target {
+                        next.boxes[index] = exit;
+                    }
+                }
+            }
+        }
+
+        if let Some(index) = self.fragile.iter().position(|pos| *pos == state.player) {
+            next.durabilities[index] -= 1;
+        }
+
+        next.player = target;
+        if let Some(exit) = self.teleport(&next, target) {
+            next.player = exit;
+        }
+
+        if let Some(index) = self.wins.iter().position(|pos| *pos == next.player) {
+            next.collected[index] = true;
+        }
+
+        Some(next)
+    }
+
+    // Applies `moves` from the start, failing on the first one that is blocked.
+    pub fn play(&self, moves: &[Move]) -> Result<State, String> {
+        let mut state = self.start.clone();
+
+        for (i, m) in moves.iter().enumerate() {
+            state = self.step(&state, *m).ok_or(format!(
+                "move {} ({}) is blocked",
+                i + 1,
+                m.letter()
+            ))?;
+        }
+
+        Ok(state)
+    }
+
+    // Where a teleport at `pos` leads, if there is one and its exit is free.
+    fn teleport(&self, state: &State, pos: IVec2) -> Option<IVec2> {
+        let exit = *self.teleports.get(&pos)?;
+
+        match exit == state.player || state.boxes.contains(&exit) {
+            true => None,
+            false => Some(exit),
+        }
+    }
+
+    pub fn has_floor(&self, state: &State, pos: IVec2) -> bool {
+        if !self.level_data.floors.contains_key(&pos) {
+            return false;
+        }
+
+        match self.fragile.iter().position(|p| *p == pos) {
+            Some(index) => state.durabilities[index] > 0,
+            None => true,
+        }
+    }
+
+    // Whether a closed wall is on the edge between `pos` and its neighbor in direction `m`.
+    pub fn blocked(&self, state: &State, pos: IVec2, m: Move) -> bool {
+        edge_wall(&self.level_data, pos, m).is_some_and(|wall| !self.opened(state, wall))
+    }
+
+    fn opened(&self, state: &State, wall: &WallData) -> bool {
+        match wall.input_dependent && self.pressed(state, wall.color) {
+            true => !wall.opened,
+            false => wall.opened,
+        }
+    }
+
+    fn pressed(&self, state: &State, color: ActivatingColor) -> bool {
+        std::iter::once(&state.player)
+            .chain(&state.boxes)
+            .any(|pos| {
+                self.level_data.floors.get(pos).is_some_and(|floor| {
+                    floor.floor_type == FloorType::Button && floor.color == color
+                })
+            })
+    }
+
+    // Buttons with the player or a box on them.
+    pub fn pressed_buttons(&self, state: &State) -> Vec<IVec2> {
+        std::iter::once(&state.player)
+            .chain(&state.boxes)
+            .filter(|pos| {
+                self.level_data
+                    .floors
+                    .get(pos)
+                    .is_some_and(|floor| floor.floor_type == FloorType::Button)
+            })
+            .copied()
+            .collect()
+    }
+
+    // The level as it looks in `state`, for drawing it with the editor's own code.
+    pub fn level_data(&self, state: &State) -> LevelData {
+        let mut level_data = self.level_data.clone();
+
+        level_data.objects.clear();
+        level_data
+            .objects
+            .insert(state.player, self.player_kind.clone());
+        for (pos, kind) in state.boxes.iter().zip(&self.box_kinds) {
+            level_data.objects.insert(*pos, kind.clone());
+        }
+
+        for (pos, durability) in self.fragile.iter().zip(&state.durabilities) {
+            match *durability > 0 {
+                true => {
+                    if let Some(floor) = level_data.floors.get_mut(pos) {
+                        floor.durability = *durability;
+                    }
+                }
+                false => {
+                    level_data.floors.remove(pos);
+                }
+            }
+        }
+
+        for (pos, collected) in self.wins.iter().zip(&state.collected) {
+            if *collected {
+                level_data.collectibles.remove(pos);
+            }
+        }
+
+        // Input dependent walls are drawn as they are right now instead of blinking.
+        for wall in level_data.walls.values_mut() {
+            for wall_data in [&mut wall.right, &mut wall.down].into_iter().flatten() {
+                wall_data.opened = self.opened(state, wall_data);
+                wall_data.input_dependent = false;
+            }
+        }
+
+        level_data
+    }
+}
+
+// The wall on the edge between `pos` and its neighbor in direction `m`.
+pub fn edge_wall(level_data: &LevelData, pos: IVec2, m: Move) -> Option<&WallData> {
+    let walls = &level_data.walls;
+
+    match m {
+        Move::Right => walls.get(&pos).and_then(|wall| wall.right.as_ref()),
+        Move::Down => walls.get(&pos).and_then(|wall| wall.down.as_ref()),
+        Move::Left => walls
+            .get(&(pos - IVec2::X))
+            .and_then(|wall| wall.right.as_ref()),
+        Move::Up => walls
+            .get(&(pos - IVec2::Y))
+            .and_then(|wall| wall.down.as_ref()),
+    }
+}
+
+fn sorted(positions: impl Iterator<Item = IVec2>) -> Vec<IVec2> {
+    let mut positions = positions.collect::<Vec<_>>();
+    positions.sort_by_key(|pos| (pos.y, pos.x));
+
+    positions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::main_state::serialization;
+
+    fn game(contents: &str) -> Game {
+        Game::new(&serialization::from_str(contents)).unwrap()
+    }
+
+    #[test]
+    fn walks_only_onto_floors() {
+        let game = game("2,1\nfloor 0,0  dur:-1 c:n\nfloor 1,0  dur:-1 c:n\nplayer 0,0  c:n\n");
+        let start = game.start();
+
+        assert_eq!(game.step(start, Move::Left), None);
+        assert_eq!(
+            game.step(start, Move::Right).unwrap().player,
+            IVec2::new(1, 0)
+        );
+    }
+
+    #[test]
+    fn pushes_one_box_at_a_time() {
+        let game = game(
+            "4,1\nfloor 0,0  dur:-1 c:n\nfloor 1,0  dur:-1 c:n\nfloor 2,0  dur:-1 c:n\n\
+             floor 3,0  dur:-1 c:n\nplayer 0,0  c:n\nbox 1,0  c:n\nbox 3,0  c:n\n",
+        );
+
+        let pushed = game.step(game.start(), Move::Right).unwrap();
+        assert_eq!(pushed.player, IVec2::new(1, 0));
+        assert!(pushed.boxes.contains(&IVec2::new(2, 0)));
+
+        // The box now stands in front of the other one.
+        assert_eq!(game.step(&pushed, Move::Right), None);
+    }
+
+    #[test]
+    fn fragile_floors_break_when_left() {
+        let game = game("2,1\nfloor 0,0  dur:1 c:n\nfloor 1,0  dur:-1 c:n\nplayer 0,0  c:n\n");
+
+        let left = game.step(game.start(), Move::Right).unwrap();
+        assert!(!game.has_floor(&left, IVec2::new(0, 0)));
+        assert_eq!(game.step(&left, Move::Left), None);
+    }
+
+    #[test]
+    fn input_dependent_walls_open_while_pressed() {
+        let game = game(
+            "2,2\nfloor 0,0  dur:-1 c:n\nfloor 1,0  dur:-1 c:n\nbutton 0,1  dur:-1 c:r\n\
+             player 0,0  c:n\nwall 0,0 r c:r closed:true inputbased:true\n",
+        );
+
+        assert_eq!(game.step(game.start(), Move::Right), None);
+
+        let pressed = game.step(game.start(), Move::Down).unwrap();
+        assert!(!game.blocked(&pressed, IVec2::ZERO, Move::Right));
+
+        // Leaving the button closes the wall again.
+        let back = game.step(&pressed, Move::Up).unwrap();
+        assert_eq!(game.step(&back, Move::Right), None);
+    }
+
+    #[test]
+    fn teleports_lead_to_each_other() {
+        let game = game(
+            "4,1\nfloor 0,0  dur:-1 c:n\nteleport 1,0  dur:-1 c:g\nteleport 3,0  dur:-1 c:g\n\
+             player 0,0  c:n\n",
+        );
+
+        let state = game.step(game.start(), Move::Right).unwrap();
+        assert_eq!(state.player, IVec2::new(3, 0));
+    }
+
+    #[test]
+    fn solved_once_every_win_is_collected() {
+        let game = game(
+            "3,1\nfloor 0,0  dur:-1 c:n\nfloor 1,0  dur:-1 c:n\nfloor 2,0  dur:-1 c:n\n\
+             player 1,0  c:n\nwin 0,0  c:n\nwin 2,0  c:n\n",
+        );
+
+        let state = game.play(&[Move::Left]).unwrap();
+        assert!(!game.solved(&state));
+
+        let state = game.play(&[Move::Left, Move::Right, Move::Right]).unwrap();
+        assert!(game.solved(&state));
+    }
+}
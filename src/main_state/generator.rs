@@ -0,0 +1,445 @@
+use std::{
+    fs,
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use ggez::{glam::IVec2, Context, GameError, GameResult};
+
+use super::{
+    analysis::Analysis,
+    config,
+    game::{Game, Move},
+    instances::{
+        collectible::{Collectible, CollectibleType},
+        floor::{Floor, FloorType},
+        object::{Object, ObjectType},
+        wall::{WallData, WallOrientation, WallType},
+        ActivatingColor, LayerData, COLOR_NAMES,
+    },
+    level_data::LevelData,
+    replay, serialization, solver,
+};
+
+const CONFIG_PATH: &str = "/generator.toml";
+
+// Out of a hundred cells, how many are left without a floor.
+const HOLE_PERCENT: u64 = 15;
+// Larger levels could never be solved in time anyway.
+const MAX_SIZE: usize = 100;
+// Candidates are only searched this far, so generating stays fast.
+const SEARCH_LIMIT: usize = 20_000;
+
+// What the generated levels should look like, read from `generator.toml`.
+#[derive(Debug, Clone)]
+pub struct GeneratorSettings {
+    pub width: i32,
+    pub height: i32,
+    pub boxes: usize,
+    pub wins: usize,
+    // Closed walls without a color.
+    pub walls: usize,
+    // Every color gets a button and a closed wall opened by it.
+    pub colors: Vec<ActivatingColor>,
+    // Pairs of teleports.
+    pub teleports: usize,
+    pub fragile: usize,
+    // The shortest solution has to be at least `min_moves` and at most `max_moves` long.
+    pub min_moves: usize,
+    pub max_moves: usize,
+    // How many candidates are tried before giving up.
+    pub attempts: usize,
+}
+
+impl GeneratorSettings {
+    pub fn new() -> GeneratorSettings {
+        GeneratorSettings {
+            width: 7,
+            height: 6,
+            boxes: 2,
+            wins: 1,
+            walls: 3,
+            colors: vec![ActivatingColor::Red],
+            teleports: 0,
+            fragile: 1,
+            min_moves: 10,
+            max_moves: 60,
+            attempts: 300,
+        }
+    }
+
+    // Reads `generator.toml` from the resources or user config directory.
+    pub fn load(ctx: &Context) -> (GeneratorSettings, Vec<String>) {
+        let mut problems = Vec::new();
+        let config = config::load(ctx, CONFIG_PATH, &mut problems);
+
+        GeneratorSettings::read(config, problems)
+    }
+
+    // The same for the command line, which has no context.
+    pub fn load_file(resources_dir: &Path) -> (GeneratorSettings, Vec<String>) {
+        let mut problems = Vec::new();
+        let config = config::load_file(resources_dir, CONFIG_PATH, &mut problems);
+
+        GeneratorSettings::read(config, problems)
+    }
+
+    fn read(
+        config: Option<toml::Value>,
+        mut problems: Vec<String>,
+    ) -> (GeneratorSettings, Vec<String>) {
+        let mut settings = GeneratorSettings::new();
+        let Some(config) = config else {
+            return (settings, problems);
+        };
+
+        let mut number = |key: &str, min: i64, value: &mut usize| {
+            let Some(config_value) = config.get(key) else {
+                return;
+            };
+
+            match config_value.as_integer().filter(|number| *number >= min) {
+                Some(number) => *value = number as usize,
+                None => problems.push(format!(
+                    "{}: `{}` must be a number of at least {}",
+                    CONFIG_PATH, key, min
+                )),
+            }
+        };
+
+        let mut width = settings.width as usize;
+        let mut height = settings.height as usize;
+        number("width", 2, &mut width);
+        number("height", 2, &mut height);
+        number("boxes", 0, &mut settings.boxes);
+        number("wins", 1, &mut settings.wins);
+        number("walls", 0, &mut settings.walls);
+        number("teleports", 0, &mut settings.teleports);
+        number("fragile", 0, &mut settings.fragile);
+        number("min_moves", 1, &mut settings.min_moves);
+        number("max_moves", 1, &mut settings.max_moves);
+        number("attempts", 1, &mut settings.attempts);
+        for (key, size) in [("width", &mut width), ("height", &mut height)] {
+            if *size > MAX_SIZE {
+                problems.push(format!(
+                    "{}: `{}` can be at most {}",
+                    CONFIG_PATH, key, MAX_SIZE
+                ));
+                *size = MAX_SIZE;
+            }
+        }
+        settings.width = width as i32;
+        settings.height = height as i32;
+
+        if let Some(colors) = config.get("colors") {
+            let names = colors.as_array().map(|colors| {
+                colors
+                    .iter()
+                    .map(|color| {
+                        COLOR_NAMES
+                            .iter()
+                            .find(|(_, name)| Some(*name) == color.as_str())
+                            .map(|(color, _)| *color)
+                    })
+                    .collect::<Option<Vec<_>>>()
+            });
+
+            match names.flatten() {
+                Some(colors) => settings.colors = colors,
+                None => problems.push(format!(
+                    "{}: `colors` must be a list of color names like [\"red\", \"blue\"]",
+                    CONFIG_PATH
+                )),
+            }
+        }
+
+        if settings.min_moves > settings.max_moves {
+            problems.push(format!(
+                "{}: `min_moves` is more than `max_moves`",
+                CONFIG_PATH
+            ));
+        }
+
+        (settings, problems)
+    }
+}
+
+// A level the solver accepted.
+#[derive(Debug, Clone)]
+pub struct Generated {
+    pub level_data: LevelData,
+    pub seed: u64,
+    pub solution: Vec<Move>,
+    // Candidates tried, this one included.
+    pub attempts: usize,
+}
+
+// A seed for when none is given.
+pub fn time_seed() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.as_secs())
+}
+
+// The same settings and seed always give the same level.
+pub fn generate(settings: &GeneratorSettings, seed: u64) -> Result<Generated, String> {
+    let mut rng = Rng(seed);
+
+    for attempt in 1..=settings.attempts {
+        let Some(level_data) = candidate(settings, &mut rng) else {
+            continue;
+        };
+
+        // Levels with boxes that can't move or wins that can't be reached aren't worth solving.
+        let analysis = Analysis::new(&level_data);
+        let unreachable_win = level_data
+            .collectibles
+            .keys()
+            .any(|pos| analysis.unreachable.contains(pos));
+        if !analysis.stuck_boxes.is_empty() || unreachable_win {
+            continue;
+        }
+
+        let Ok(game) = Game::new(&level_data) else {
+            continue;
+        };
+        let search = solver::search(&game, SEARCH_LIMIT);
+
+        if let Some(solution) = search.solution {
+            if (settings.min_moves..=settings.max_moves).contains(&solution.len()) {
+                return Ok(Generated {
+                    level_data,
+                    seed,
+                    solution,
+                    attempts: attempt,
+                });
+            }
+        }
+    }
+
+    Err(format!(
+        "seed {}: no level solved in {}-{} moves after {} attempts",
+        seed, settings.min_moves, settings.max_moves, settings.attempts
+    ))
+}
+
+// Floors with holes in them, and everything else on random free cells.
+fn candidate(settings: &GeneratorSettings, rng: &mut Rng) -> Option<LevelData> {
+    let mut level_data = LevelData::new();
+
+    // Cells are kept in reading order, because the maps of `LevelData` have none
+    // and the same seed has to pick the same cells.
+    let mut free = Vec::new();
+    for y in 0..settings.height {
+        for x in 0..settings.width {
+            if rng.below(100) >= HOLE_PERCENT {
+                free.push(IVec2::new(x, y));
+            }
+        }
+    }
+    for pos in &free {
+        level_data
+            .floors
+            .insert(*pos, Floor::default(FloorType::Normal));
+    }
+
+    let mut edges = free
+        .iter()
+        .flat_map(|pos| {
+            [
+                (*pos, WallOrientation::Right, IVec2::X),
+                (*pos, WallOrientation::Down, IVec2::Y),
+            ]
+        })
+        .filter(|(pos, _, offset)| level_data.floors.contains_key(&(*pos + *offset)))
+        .map(|(pos, orientation, _)| (pos, orientation))
+        .collect::<Vec<_>>();
+
+    let needed = 1
+        + settings.boxes
+        + settings.wins
+        + settings.colors.len()
+        + settings.teleports * 2
+        + settings.fragile;
+    if free.len() < needed || edges.len() < settings.walls + settings.colors.len() {
+        return None;
+    }
+
+    let mut take = |rng: &mut Rng| free.swap_remove(rng.below(free.len() as u64) as usize);
+
+    let player = take(rng);
+    level_data.insert(
+        player,
+        LayerData::Object(Object::default(ObjectType::Player)),
+        WallOrientation::Down,
+    );
+
+    for _ in 0..settings.boxes {
+        level_data.insert(
+            take(rng),
+            LayerData::Object(Object::default(ObjectType::Box)),
+            WallOrientation::Down,
+        );
+    }
+
+    for _ in 0..settings.wins {
+        level_data.insert(
+            take(rng),
+            LayerData::Collectible(Collectible::default(CollectibleType::Win)),
+            WallOrientation::Down,
+        );
+    }
+
+    for color in &settings.colors {
+        level_data.insert(
+            take(rng),
+            LayerData::Floor(Floor::new(FloorType::Button, *color)),
+            WallOrientation::Down,
+        );
+
+        let (pos, orientation) = edges.swap_remove(rng.below(edges.len() as u64) as usize);
+        level_data.insert(
+            pos,
+            LayerData::Wall(WallData::new(WallType::Normal, *color, true, false)),
+            orientation,
+        );
+    }
+
+    for _ in 0..settings.walls {
+        let (pos, orientation) = edges.swap_remove(rng.below(edges.len() as u64) as usize);
+        level_data.insert(
+            pos,
+            LayerData::Wall(WallData::default(WallType::Normal)),
+            orientation,
+        );
+    }
+
+    // Teleports of one color lead to each other, so every pair gets its own color
+    // while the colors in use last.
+    for i in 0..settings.teleports {
+        let color = settings
+            .colors
+            .get(i)
+            .copied()
+            .unwrap_or(ActivatingColor::None);
+
+        for _ in 0..2 {
+            level_data.insert(
+                take(rng),
+                LayerData::Floor(Floor::new(FloorType::Teleport, color)),
+                WallOrientation::Down,
+            );
+        }
+    }
+
+    for _ in 0..settings.fragile {
+        let mut floor = Floor::default(FloorType::Normal);
+        floor.durability = 1 + rng.below(2) as i32;
+        level_data.insert(take(rng), LayerData::Floor(floor), WallOrientation::Down);
+    }
+
+    Some(level_data)
+}
+
+// Generates `count` levels with the seeds from `seed` on into `dir`, each with
+// its solution as a replay next to it.
+pub fn generate_files(resources_dir: &Path, dir: &Path, count: u64, seed: u64) -> GameResult {
+    let (settings, problems) = GeneratorSettings::load_file(resources_dir);
+    if !problems.is_empty() {
+        return Err(GameError::CustomError(problems.join("\n")));
+    }
+
+    fs::create_dir_all(dir)?;
+
+    let mut failures = Vec::new();
+    for seed in seed..seed + count {
+        let generated = match generate(&settings, seed) {
+            Ok(generated) => generated,
+            Err(error) => {
+                failures.push(error);
+                continue;
+            }
+        };
+
+        let level = dir.join(format!("generated_{}", seed));
+        let saved = serialization::save(&generated.level_data, &level)
+            .and_then(|_| replay::save(&replay::path(&level), &generated.solution));
+
+        match saved {
+            Ok(()) => println!(
+                "{}: solved in {} moves, found after {} attempts",
+                level.display(),
+                generated.solution.len(),
+                generated.attempts
+            ),
+            Err(error) => failures.push(format!("{}: {}", level.display(), error)),
+        }
+    }
+
+    match failures.is_empty() {
+        true => Ok(()),
+        false => Err(GameError::CustomError(failures.join("\n"))),
+    }
+}
+
+// Splitmix64, small enough to keep here and fixed, so seeds give the same levels everywhere.
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e3779b97f4a7c15);
+
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    fn below(&mut self, n: u64) -> u64 {
+        self.next() % n
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings(contents: &str) -> (GeneratorSettings, Vec<String>) {
+        let mut problems = Vec::new();
+        let config = config::parse(CONFIG_PATH, contents, &mut problems);
+
+        GeneratorSettings::read(config, problems)
+    }
+
+    fn sorted_lines(level_data: &LevelData) -> Vec<String> {
+        let mut lines = serialization::to_string(level_data)
+            .lines()
+            .map(String::from)
+            .collect::<Vec<_>>();
+        lines.sort();
+
+        lines
+    }
+
+    #[test]
+    fn the_same_seed_gives_the_same_level() {
+        let settings = GeneratorSettings::new();
+        let first = generate(&settings, 7).unwrap();
+        let second = generate(&settings, 7).unwrap();
+
+        assert_eq!(
+            sorted_lines(&first.level_data),
+            sorted_lines(&second.level_data)
+        );
+        assert_eq!(first.solution, second.solution);
+        assert_eq!(first.attempts, second.attempts);
+    }
+
+    #[test]
+    fn limits_the_level_size() {
+        let (settings, problems) = settings("width = 5000000000\nheight = 12");
+
+        assert_eq!(problems, ["/generator.toml: `width` can be at most 100"]);
+        assert_eq!((settings.width, settings.height), (100, 12));
+    }
+}
@@ -0,0 +1,112 @@
+use ggez::{
+    graphics::{Canvas, Color, DrawMode, DrawParam, Mesh, MeshBuilder, Rect},
+    mint::Point2,
+    Context, GameResult,
+};
+
+use super::{level_data::LevelData, MainState};
+
+pub struct Grid {
+    pub visible: bool,
+}
+
+impl Grid {
+    const MAJOR_EVERY: i32 = 8;
+    // Closer lines than this many screen pixels only blur into a solid color.
+    const MIN_SPACING: f32 = 4.0;
+
+    pub fn new() -> Grid {
+        Grid { visible: true }
+    }
+
+    pub fn toggle(&mut self) {
+        self.visible = !self.visible;
+    }
+
+    // Draws in world coordinates: cell lines over the visible area, the origin axes
+    // and the level rectangle that `save` writes into the header.
+    pub fn draw(
+        &self,
+        ctx: &Context,
+        canvas: &mut Canvas,
+        screen_rect: Rect,
+        level_data: &LevelData,
+    ) -> GameResult {
+        let size = MainState::CELL_SIZE as f32;
+        let pixel = screen_rect.w / ctx.gfx.size().0;
+        let mut builder = MeshBuilder::new();
+
+        let (left, right) = (screen_rect.left(), screen_rect.right());
+        let (top, bottom) = (screen_rect.top(), screen_rect.bottom());
+
+        let spacing = size / pixel;
+        let step = match spacing < Self::MIN_SPACING {
+            true => Self::MAJOR_EVERY,
+            false => 1,
+        };
+
+        if self.visible && spacing * step as f32 >= Self::MIN_SPACING {
+            let line_color = |cell: i32| match cell % Self::MAJOR_EVERY == 0 {
+                true => Color::new(1.0, 1.0, 1.0, 0.35),
+                false => Color::new(1.0, 1.0, 1.0, 0.12),
+            };
+
+            for x in (left / size).floor() as i32..=(right / size).ceil() as i32 {
+                if x % step != 0 {
+                    continue;
+                }
+                let x_pos = x as f32 * size;
+                builder.line(
+                    &[
+                        Point2 { x: x_pos, y: top },
+                        Point2 {
+                            x: x_pos,
+                            y: bottom,
+                        },
+                    ],
+                    pixel,
+                    line_color(x),
+                )?;
+            }
+
+            for y in (top / size).floor() as i32..=(bottom / size).ceil() as i32 {
+                if y % step != 0 {
+                    continue;
+                }
+                let y_pos = y as f32 * size;
+                builder.line(
+                    &[Point2 { x: left, y: y_pos }, Point2 { x: right, y: y_pos }],
+                    pixel,
+                    line_color(y),
+                )?;
+            }
+        }
+
+        builder.line(
+            &[Point2 { x: left, y: 0.0 }, Point2 { x: right, y: 0.0 }],
+            pixel * 2.0,
+            Color::RED,
+        )?;
+        builder.line(
+            &[Point2 { x: 0.0, y: top }, Point2 { x: 0.0, y: bottom }],
+            pixel * 2.0,
+            Color::GREEN,
+        )?;
+
+        let dimensions = level_data.dimensions();
+        builder.rectangle(
+            DrawMode::stroke(pixel * 2.0),
+            Rect::new(
+                0.0,
+                0.0,
+                (dimensions.x + 1) as f32 * size,
+                (dimensions.y + 1) as f32 * size,
+            ),
+            Color::YELLOW,
+        )?;
+
+        canvas.draw(&Mesh::from_data(ctx, builder.build()), DrawParam::default());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,196 @@
+use ggez::glam::IVec2;
+
+use super::{
+    instances::{Layer, LayerSlot},
+    level_data::LevelData,
+};
+
+#[derive(Debug, Clone)]
+pub struct Change {
+    pub pos: IVec2,
+    pub before: LayerSlot,
+    pub after: LayerSlot,
+}
+
+// Changes undone and redone together, numbered in the order they were committed.
+#[derive(Debug, Clone)]
+struct Step {
+    id: u64,
+    changes: Vec<Change>,
+}
+
+// Changes are collected into a pending group until `commit` is called,
+// so a whole mouse stroke is undone at once.
+#[derive(Debug, Default)]
+pub struct History {
+    undo: Vec<Step>,
+    redo: Vec<Step>,
+    pending: Vec<Change>,
+    last_id: u64,
+}
+
+impl History {
+    pub fn new() -> History {
+        History::default()
+    }
+
+    // Returns whether the edit changed anything.
+    pub fn edit(
+        &mut self,
+        level_data: &mut LevelData,
+        pos: IVec2,
+        layer: Layer<(), (), (), ()>,
+        edit: impl FnOnce(&mut LevelData),
+    ) -> bool {
+        let before = level_data.slot(pos, layer);
+        edit(level_data);
+        let after = level_data.slot(pos, layer);
+
+        if before == after {
+            return false;
+        }
+
+        self.pending.push(Change { pos, before, after });
+        true
+    }
+
+    pub fn commit(&mut self) {
+        if self.pending.is_empty() {
+            return;
+        }
+
+        self.last_id += 1;
+        self.undo.push(Step {
+            id: self.last_id,
+            changes: std::mem::take(&mut self.pending),
+        });
+        self.redo.clear();
+    }
+
+    // Identifies the level as the history left it, so undoing back to a saved
+    // level can be told apart from changing it. None while changes are pending.
+    pub fn state(&self) -> Option<u64> {
+        match self.pending.is_empty() {
+            true => Some(self.undo.last().map_or(0, |step| step.id)),
+            false => None,
+        }
+    }
+
+    pub fn undo(&mut self, level_data: &mut LevelData) -> bool {
+        self.commit();
+
+        let Some(step) = self.undo.pop() else {
+            return false;
+        };
+
+        for change in step.changes.iter().rev() {
+            level_data.set_slot(change.pos, change.before.clone());
+        }
+
+        self.redo.push(step);
+        true
+    }
+
+    pub fn redo(&mut self, level_data: &mut LevelData) -> bool {
+        self.commit();
+
+        let Some(step) = self.redo.pop() else {
+            return false;
+        };
+
+        for change in &step.changes {
+            level_data.set_slot(change.pos, change.after.clone());
+        }
+
+        self.undo.push(step);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::main_state::instances::{
+        floor::{Floor, FloorType},
+        wall::WallOrientation,
+        LayerData,
+    };
+
+    // Puts a floor at `x`, 0 like one mouse stroke cell after another.
+    fn paint(history: &mut History, level_data: &mut LevelData, x: i32) {
+        let pos = IVec2::new(x, 0);
+        history.edit(level_data, pos, Layer::Floor(()), |level_data| {
+            level_data.insert(
+                pos,
+                LayerData::Floor(Floor::default(FloorType::Normal)),
+                WallOrientation::Down,
+            )
+        });
+    }
+
+    #[test]
+    fn undoes_and_redoes_a_stroke_at_once() {
+        let mut history = History::new();
+        let mut level_data = LevelData::new();
+
+        for x in 0..3 {
+            paint(&mut history, &mut level_data, x);
+        }
+        history.commit();
+
+        assert!(history.undo(&mut level_data));
+        assert!(level_data.floors.is_empty());
+        assert!(!history.undo(&mut level_data));
+
+        assert!(history.redo(&mut level_data));
+        assert_eq!(level_data.floors.len(), 3);
+        assert!(!history.redo(&mut level_data));
+    }
+
+    #[test]
+    fn undo_commits_a_stroke_still_pending() {
+        let mut history = History::new();
+        let mut level_data = LevelData::new();
+
+        paint(&mut history, &mut level_data, 0);
+
+        assert!(history.undo(&mut level_data));
+        assert!(level_data.floors.is_empty());
+    }
+
+    #[test]
+    fn undoing_back_returns_to_the_same_state() {
+        let mut history = History::new();
+        let mut level_data = LevelData::new();
+
+        paint(&mut history, &mut level_data, 0);
+        assert_eq!(history.state(), None);
+        history.commit();
+        let saved = history.state();
+
+        paint(&mut history, &mut level_data, 1);
+        history.commit();
+        assert_ne!(history.state(), saved);
+
+        history.undo(&mut level_data);
+        assert_eq!(history.state(), saved);
+    }
+
+    #[test]
+    fn a_new_edit_drops_the_redo_steps() {
+        let mut history = History::new();
+        let mut level_data = LevelData::new();
+
+        paint(&mut history, &mut level_data, 0);
+        history.commit();
+        history.undo(&mut level_data);
+
+        paint(&mut history, &mut level_data, 1);
+        history.commit();
+
+        assert!(!history.redo(&mut level_data));
+        assert_eq!(history.state(), Some(2));
+        assert!(level_data.floors.contains_key(&IVec2::new(1, 0)));
+        assert!(!level_data.floors.contains_key(&IVec2::new(0, 0)));
+    }
+}
@@ -5,25 +5,26 @@ use super::instances::{
     wall::{WallData, WallType},
     ActivatingColor, Layer, LayerData,
 };
+use super::keybindings::{Action, KeyBindings};
 use ggez::winit::event::VirtualKeyCode;
 
-const OBJECT_ITEMS: [ObjectType; 3] = [
+pub const OBJECT_ITEMS: [ObjectType; 3] = [
     ObjectType::Player,  //
     ObjectType::Box,     //
     ObjectType::TeleBox, //
 ];
 
-const FLOOR_ITEMS: [FloorType; 3] = [
+pub const FLOOR_ITEMS: [FloorType; 3] = [
     FloorType::Normal,   //
     FloorType::Button,   //
     FloorType::Teleport, //
 ];
 
-const WALL_ITEMS: [WallType; 1] = [
+pub const WALL_ITEMS: [WallType; 1] = [
     WallType::Normal, //
 ];
 
-const COLLECTIBLE_ITEMS: [CollectibleType; 1] = [
+pub const COLLECTIBLE_ITEMS: [CollectibleType; 1] = [
     CollectibleType::Win, //
 ];
 
@@ -42,14 +43,16 @@ pub struct InputHandler {
     wall_opened: bool,
 
     floor_durability: i32,
-    waiting_for_durability_input: bool,
+    durability_input: Option<String>,
+
+    key_bindings: KeyBindings,
 
     pub request_save: bool,
     pub request_load: bool,
 }
 
 impl InputHandler {
-    pub fn new() -> Self {
+    pub fn new(key_bindings: KeyBindings) -> Self {
         InputHandler {
             layer: Layer::Object(()),
 
@@ -64,7 +67,9 @@ impl InputHandler {
             wall_opened: false,
 
             floor_durability: 1,
-            waiting_for_durability_input: false,
+            durability_input: None,
+
+            key_bindings,
 
             request_save: false,
             request_load: false,
@@ -75,64 +80,91 @@ impl InputHandler {
         self.layer
     }
 
-    pub fn handle_input(&mut self, input: ggez::input::keyboard::KeyInput) {
+    // Returns the actions that are not about the brush, so the caller can handle them.
+    pub fn handle_input(&mut self, input: ggez::input::keyboard::KeyInput) -> Option<Action> {
         // println!(
         //     "value: {:?}, scancode: {}, keycode: {:?}",
         //     "nowe", input.scancode, input.keycode
         // );
 
-        let Some(keycode) = input.keycode else {
-            return;
-        };
+        let keycode = input.keycode?;
+
+        if let Some(entry) = &mut self.durability_input {
+            let digit = match keycode {
+                VirtualKeyCode::Key0 | VirtualKeyCode::Numpad0 => Some('0'),
+                VirtualKeyCode::Key1 | VirtualKeyCode::Numpad1 => Some('1'),
+                VirtualKeyCode::Key2 | VirtualKeyCode::Numpad2 => Some('2'),
+                VirtualKeyCode::Key3 | VirtualKeyCode::Numpad3 => Some('3'),
+                VirtualKeyCode::Key4 | VirtualKeyCode::Numpad4 => Some('4'),
+                VirtualKeyCode::Key5 | VirtualKeyCode::Numpad5 => Some('5'),
+                VirtualKeyCode::Key6 | VirtualKeyCode::Numpad6 => Some('6'),
+                VirtualKeyCode::Key7 | VirtualKeyCode::Numpad7 => Some('7'),
+                VirtualKeyCode::Key8 | VirtualKeyCode::Numpad8 => Some('8'),
+                VirtualKeyCode::Key9 | VirtualKeyCode::Numpad9 => Some('9'),
+                _ => None,
+            };
+
+            if let Some(digit) = digit {
+                entry.push(digit);
+                // Too large numbers are capped, the prompt shows what will be set.
+                if entry.parse::<i32>().is_err() {
+                    *entry = i32::MAX.to_string();
+                }
+                return None;
+            }
 
-        if self.waiting_for_durability_input {
             match keycode {
-                VirtualKeyCode::Key1 => self.floor_durability = 1,
-                VirtualKeyCode::Key2 => self.floor_durability = 2,
-                VirtualKeyCode::Key3 => self.floor_durability = 3,
+                VirtualKeyCode::Back => {
+                    entry.pop();
+                }
+
+                VirtualKeyCode::Return | VirtualKeyCode::NumpadEnter => {
+                    if let Ok(durability) = entry.parse::<i32>() {
+                        if durability > 0 {
+                            self.floor_durability = durability;
+                        }
+                    }
+                    self.durability_input = None;
+                }
+                VirtualKeyCode::I | VirtualKeyCode::Minus | VirtualKeyCode::NumpadSubtract => {
+                    self.floor_durability = Floor::INDESTRUCTIBLE;
+                    self.durability_input = None;
+                }
 
-                _ => (),
+                _ => self.durability_input = None,
             }
 
-            self.waiting_for_durability_input = false;
-            return;
+            return None;
         }
 
-        match keycode {
-            VirtualKeyCode::Key1 => self.layer = Layer::Object(()),
-            VirtualKeyCode::Key2 => self.layer = Layer::Floor(()),
-            VirtualKeyCode::Key3 => self.layer = Layer::Wall(()),
-            VirtualKeyCode::Key4 => self.layer = Layer::Collectible(()),
-
-            VirtualKeyCode::Q => self.set_current_item(0),
-            VirtualKeyCode::W => self.set_current_item(1),
-            VirtualKeyCode::E => self.set_current_item(2),
-            VirtualKeyCode::R => self.set_current_item(3),
-            VirtualKeyCode::T => self.set_current_item(4),
-            VirtualKeyCode::Y => self.set_current_item(5),
-            VirtualKeyCode::U => self.set_current_item(6),
-            VirtualKeyCode::I => self.set_current_item(7),
-            VirtualKeyCode::O => self.set_current_item(8),
-            VirtualKeyCode::P => self.set_current_item(9),
-
-            VirtualKeyCode::A => self.color = ActivatingColor::None,
-            VirtualKeyCode::S => self.color = ActivatingColor::Red,
-            VirtualKeyCode::D => self.color = ActivatingColor::Blue,
-            VirtualKeyCode::F => self.color = ActivatingColor::Green,
-            VirtualKeyCode::G => self.color = ActivatingColor::Yellow,
-            VirtualKeyCode::H => self.color = ActivatingColor::Cyan,
-            VirtualKeyCode::J => self.color = ActivatingColor::Pink,
-
-            VirtualKeyCode::N => self.wall_input_dependent = !self.wall_input_dependent,
-            VirtualKeyCode::M => self.wall_opened = !self.wall_opened,
-
-            VirtualKeyCode::B => self.waiting_for_durability_input = true,
-
-            VirtualKeyCode::Return => self.request_save = true,
-            VirtualKeyCode::Space => self.request_load = true,
-
-            _ => (),
+        let action = self.key_bindings.action(&input)?;
+
+        match action {
+            Action::LayerObject => self.layer = Layer::Object(()),
+            Action::LayerFloor => self.layer = Layer::Floor(()),
+            Action::LayerWall => self.layer = Layer::Wall(()),
+            Action::LayerCollectible => self.layer = Layer::Collectible(()),
+
+            Action::Item(index) => self.set_current_item(index),
+            Action::Color(color) => self.color = color,
+
+            Action::ToggleInputDependent => self.wall_input_dependent = !self.wall_input_dependent,
+            Action::ToggleClosed => self.wall_opened = !self.wall_opened,
+
+            Action::Durability => self.durability_input = Some(String::new()),
+
+            Action::Save => self.request_save = true,
+            Action::Load => self.request_load = true,
+
+            _ => return Some(action),
         }
+
+        None
+    }
+
+    // The action bound to `input`, without doing anything.
+    pub fn bound_action(&self, input: &ggez::input::keyboard::KeyInput) -> Option<Action> {
+        self.key_bindings.action(input)
     }
 
     pub fn set_current_item(&mut self, index: usize) {
@@ -163,6 +195,43 @@ impl InputHandler {
         }
     }
 
+    pub fn durability_input(&self) -> Option<&str> {
+        self.durability_input.as_deref()
+    }
+
+    pub fn pick(&mut self, data: LayerData) {
+        match data {
+            LayerData::Object(object) => {
+                self.layer = Layer::Object(());
+                self.object_item = object.object_type;
+                self.color = object.color;
+            }
+
+            LayerData::Floor(floor) => {
+                self.layer = Layer::Floor(());
+                self.floor_item = floor.floor_type;
+                self.color = floor.color;
+                if let FloorType::Normal = floor.floor_type {
+                    self.floor_durability = floor.durability;
+                }
+            }
+
+            LayerData::Wall(wall) => {
+                self.layer = Layer::Wall(());
+                self.wall_item = wall.wall_type;
+                self.color = wall.color;
+                self.wall_input_dependent = wall.input_dependent;
+                self.wall_opened = wall.opened;
+            }
+
+            LayerData::Collectible(collectible) => {
+                self.layer = Layer::Collectible(());
+                self.collectible_item = collectible.collectible_type;
+                self.color = collectible.color;
+            }
+        }
+    }
+
     pub fn get_data(&self) -> LayerData {
         match self.layer {
             Layer::Object(()) => {
@@ -0,0 +1,342 @@
+use ggez::{
+    glam::IVec2,
+    graphics::{Canvas, Color, DrawMode, DrawParam, Mesh, Rect, Text},
+    mint::Point2,
+    Context, GameResult,
+};
+
+use super::{
+    document::Document,
+    input_handler::{COLLECTIBLE_ITEMS, FLOOR_ITEMS, OBJECT_ITEMS, WALL_ITEMS},
+    instances::{wall::WallOrientation, Layer, COLORS},
+    level_data::LevelData,
+    MainState,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Field {
+    ObjectType,
+    ObjectColor,
+
+    FloorType,
+    FloorColor,
+    FloorDurability,
+
+    WallType(WallOrientation),
+    WallColor(WallOrientation),
+    WallInputDependent(WallOrientation),
+    WallClosed(WallOrientation),
+
+    CollectibleType,
+    CollectibleColor,
+}
+
+impl Field {
+    fn layer(&self) -> Layer<(), (), (), ()> {
+        match self {
+            Field::ObjectType | Field::ObjectColor => Layer::Object(()),
+            Field::FloorType | Field::FloorColor | Field::FloorDurability => Layer::Floor(()),
+            Field::WallType(_)
+            | Field::WallColor(_)
+            | Field::WallInputDependent(_)
+            | Field::WallClosed(_) => Layer::Wall(()),
+            Field::CollectibleType | Field::CollectibleColor => Layer::Collectible(()),
+        }
+    }
+}
+
+fn cycle<T: Copy + PartialEq>(items: &[T], current: T, delta: i32) -> T {
+    let index = items.iter().position(|item| *item == current).unwrap_or(0) as i32;
+    let index = (index + delta).rem_euclid(items.len() as i32);
+
+    items[index as usize]
+}
+
+#[derive(Debug, Default)]
+pub struct Inspector {
+    pub open: bool,
+    cell: Option<IVec2>,
+    field: usize,
+}
+
+impl Inspector {
+    pub fn new() -> Inspector {
+        Inspector::default()
+    }
+
+    pub fn toggle(&mut self) {
+        self.open = !self.open;
+    }
+
+    pub fn inspect(&mut self, pos: IVec2) {
+        if self.cell != Some(pos) {
+            self.cell = Some(pos);
+            self.field = 0;
+        }
+    }
+
+    fn fields(&self, level_data: &LevelData) -> Vec<Field> {
+        let mut fields = Vec::new();
+        let Some(pos) = self.cell else {
+            return fields;
+        };
+
+        if level_data.objects.contains_key(&pos) {
+            fields.extend([Field::ObjectType, Field::ObjectColor]);
+        }
+
+        if level_data.floors.contains_key(&pos) {
+            fields.extend([Field::FloorType, Field::FloorColor, Field::FloorDurability]);
+        }
+
+        if let Some(wall) = level_data.walls.get(&pos) {
+            for (orientation, wall_data) in [
+                (WallOrientation::Right, &wall.right),
+                (WallOrientation::Down, &wall.down),
+            ] {
+                if wall_data.is_some() {
+                    fields.extend([
+                        Field::WallType(orientation),
+                        Field::WallColor(orientation),
+                        Field::WallInputDependent(orientation),
+                        Field::WallClosed(orientation),
+                    ]);
+                }
+            }
+        }
+
+        if level_data.collectibles.contains_key(&pos) {
+            fields.extend([Field::CollectibleType, Field::CollectibleColor]);
+        }
+
+        fields
+    }
+
+    pub fn select(&mut self, level_data: &LevelData, delta: i32) {
+        let count = self.fields(level_data).len() as i32;
+        if count == 0 {
+            return;
+        }
+
+        self.field = (self.field as i32 + delta).rem_euclid(count) as usize;
+    }
+
+    // The layer of the selected field.
+    pub fn layer(&self, level_data: &LevelData) -> Option<Layer> {
+        self.fields(level_data)
+            .get(self.field)
+            .map(|field| field.layer())
+    }
+
+    pub fn adjust(&mut self, document: &mut Document, delta: i32) {
+        let (Some(pos), Some(field)) = (
+            self.cell,
+            self.fields(&document.level_data).get(self.field).copied(),
+        ) else {
+            return;
+        };
+
+        document.edit(pos, field.layer(), |level_data| {
+            match field {
+                Field::ObjectType | Field::ObjectColor => {
+                    let Some(object) = level_data.objects.get_mut(&pos) else {
+                        return;
+                    };
+                    match field {
+                        Field::ObjectType => {
+                            object.object_type = cycle(&OBJECT_ITEMS, object.object_type, delta)
+                        }
+                        _ => object.color = cycle(&COLORS, object.color, delta),
+                    }
+                }
+
+                Field::FloorType | Field::FloorColor | Field::FloorDurability => {
+                    let Some(floor) = level_data.floors.get_mut(&pos) else {
+                        return;
+                    };
+                    match field {
+                        Field::FloorType => {
+                            floor.floor_type = cycle(&FLOOR_ITEMS, floor.floor_type, delta)
+                        }
+                        Field::FloorColor => floor.color = cycle(&COLORS, floor.color, delta),
+                        _ => floor.step_durability(delta),
+                    }
+                }
+
+                Field::WallType(orientation)
+                | Field::WallColor(orientation)
+                | Field::WallInputDependent(orientation)
+                | Field::WallClosed(orientation) => {
+                    let Some(wall) = level_data.walls.get_mut(&pos) else {
+                        return;
+                    };
+                    let wall_data = match orientation {
+                        WallOrientation::Right => &mut wall.right,
+                        WallOrientation::Down => &mut wall.down,
+                    };
+                    let Some(wall_data) = wall_data else {
+                        return;
+                    };
+                    match field {
+                        Field::WallType(_) => {
+                            wall_data.wall_type = cycle(&WALL_ITEMS, wall_data.wall_type, delta)
+                        }
+                        Field::WallColor(_) => {
+                            wall_data.color = cycle(&COLORS, wall_data.color, delta)
+                        }
+                        Field::WallInputDependent(_) => {
+                            wall_data.input_dependent = !wall_data.input_dependent
+                        }
+                        _ => wall_data.opened = !wall_data.opened,
+                    }
+                }
+
+                Field::CollectibleType | Field::CollectibleColor => {
+                    let Some(collectible) = level_data.collectibles.get_mut(&pos) else {
+                        return;
+                    };
+                    match field {
+                        Field::CollectibleType => {
+                            collectible.collectible_type =
+                                cycle(&COLLECTIBLE_ITEMS, collectible.collectible_type, delta)
+                        }
+                        _ => collectible.color = cycle(&COLORS, collectible.color, delta),
+                    }
+                }
+            };
+        });
+
+        document.commit();
+    }
+
+    fn describe(field: Field, pos: IVec2, level_data: &LevelData) -> String {
+        let wall_data = |orientation| {
+            let wall = level_data.walls.get(&pos)?;
+            match orientation {
+                WallOrientation::Right => wall.right.as_ref(),
+                WallOrientation::Down => wall.down.as_ref(),
+            }
+        };
+
+        let value = match field {
+            Field::ObjectType => level_data
+                .objects
+                .get(&pos)
+                .map(|o| format!("{:?}", o.object_type)),
+            Field::ObjectColor => level_data
+                .objects
+                .get(&pos)
+                .map(|o| format!("{:?}", o.color)),
+
+            Field::FloorType => level_data
+                .floors
+                .get(&pos)
+                .map(|f| format!("{:?}", f.floor_type)),
+            Field::FloorColor => level_data
+                .floors
+                .get(&pos)
+                .map(|f| format!("{:?}", f.color)),
+            Field::FloorDurability => {
+                level_data
+                    .floors
+                    .get(&pos)
+                    .map(|f| match f.is_indestructible() {
+                        true => String::from("indestructible"),
+                        false => f.durability.to_string(),
+                    })
+            }
+
+            Field::WallType(o) => wall_data(o).map(|w| format!("{:?}", w.wall_type)),
+            Field::WallColor(o) => wall_data(o).map(|w| format!("{:?}", w.color)),
+            Field::WallInputDependent(o) => wall_data(o).map(|w| w.input_dependent.to_string()),
+            Field::WallClosed(o) => wall_data(o).map(|w| (!w.opened).to_string()),
+
+            Field::CollectibleType => level_data
+                .collectibles
+                .get(&pos)
+                .map(|c| format!("{:?}", c.collectible_type)),
+            Field::CollectibleColor => level_data
+                .collectibles
+                .get(&pos)
+                .map(|c| format!("{:?}", c.color)),
+        }
+        .unwrap_or_default();
+
+        let name = match field {
+            Field::ObjectType => String::from("object"),
+            Field::FloorType => String::from("floor"),
+            Field::CollectibleType => String::from("collectible"),
+            Field::WallType(o) => format!("wall {}", o.name()),
+
+            Field::ObjectColor
+            | Field::FloorColor
+            | Field::WallColor(_)
+            | Field::CollectibleColor => String::from("  color"),
+            Field::FloorDurability => String::from("  durability"),
+            Field::WallInputDependent(_) => String::from("  inputbased"),
+            Field::WallClosed(_) => String::from("  closed"),
+        };
+
+        format!("{}: {}", name, value)
+    }
+
+    pub fn draw(&self, ctx: &Context, canvas: &mut Canvas, level_data: &LevelData) -> GameResult {
+        let Some(pos) = self.cell else {
+            return Ok(());
+        };
+
+        let mut lines = vec![format!("cell {},{}", pos.x, pos.y)];
+        let fields = self.fields(level_data);
+
+        if fields.is_empty() {
+            lines.push(String::from("empty"));
+        }
+
+        for (i, field) in fields.iter().enumerate() {
+            let marker = if i == self.field { "> " } else { "  " };
+            lines.push(format!(
+                "{}{}",
+                marker,
+                Self::describe(*field, pos, level_data)
+            ));
+        }
+
+        let text = Text::new(lines.join("\n"));
+        let bounds = text.measure(ctx)?;
+        let (width, _) = ctx.gfx.size();
+
+        let panel = Rect::new(
+            width - bounds.x - 16.0,
+            0.0,
+            bounds.x + 16.0,
+            bounds.y + 16.0,
+        );
+        let background =
+            Mesh::new_rectangle(ctx, DrawMode::fill(), panel, Color::new(0.0, 0.0, 0.0, 0.8))?;
+
+        canvas.draw(&background, DrawParam::default());
+        canvas.draw(
+            &text,
+            DrawParam::default().dest(Point2 {
+                x: panel.x + 8.0,
+                y: panel.y + 8.0,
+            }),
+        );
+
+        Ok(())
+    }
+
+    pub fn draw_cell(&self, ctx: &Context, canvas: &mut Canvas) -> GameResult {
+        let Some(pos) = self.cell else {
+            return Ok(());
+        };
+
+        let size = MainState::CELL_SIZE as f32;
+        let rect = Rect::new(pos.x as f32 * size, pos.y as f32 * size, size, size);
+        let outline = Mesh::new_rectangle(ctx, DrawMode::stroke(1.0), rect, Color::WHITE)?;
+
+        canvas.draw(&outline, DrawParam::default());
+
+        Ok(())
+    }
+}
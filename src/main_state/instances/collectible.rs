@@ -1,12 +1,12 @@
 use super::ActivatingColor;
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum CollectibleType {
     Win,
 }
 
 #[non_exhaustive]
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Collectible {
     pub collectible_type: CollectibleType,
     pub color: ActivatingColor,
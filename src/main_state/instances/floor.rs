@@ -1,6 +1,6 @@
 use super::ActivatingColor;
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum FloorType {
     Normal,
     Button,
@@ -8,7 +8,7 @@ pub enum FloorType {
 }
 
 #[non_exhaustive]
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Floor {
     pub floor_type: FloorType,
     pub color: ActivatingColor,
@@ -16,11 +16,15 @@ pub struct Floor {
 }
 
 impl Floor {
+    // Any negative durability is treated as indestructible,
+    // `-1` is the value written to level files.
+    pub const INDESTRUCTIBLE: i32 = -1;
+
     pub fn new(floor_type: FloorType, color: ActivatingColor) -> Floor {
         Floor {
             floor_type,
             color,
-            durability: -1,
+            durability: Floor::INDESTRUCTIBLE,
         }
     }
 
@@ -28,7 +32,21 @@ impl Floor {
         Floor {
             floor_type,
             color: ActivatingColor::None,
-            durability: -1,
+            durability: Floor::INDESTRUCTIBLE,
         }
     }
+
+    pub fn is_indestructible(&self) -> bool {
+        self.durability < 0
+    }
+
+    // Steps through 1, 2, 3, ... and goes to indestructible below 1.
+    pub fn step_durability(&mut self, delta: i32) {
+        let durability = self.durability.max(0).saturating_add(delta);
+
+        self.durability = match durability > 0 {
+            true => durability,
+            false => Floor::INDESTRUCTIBLE,
+        };
+    }
 }
@@ -4,7 +4,7 @@ use self::{
     collectible::Collectible,
     floor::Floor,
     object::Object,
-    wall::{Wall, WallData, WallOrientation},
+    wall::{Wall, WallData},
 };
 
 pub mod collectible;
@@ -12,7 +12,7 @@ pub mod floor;
 pub mod object;
 pub mod wall;
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum ActivatingColor {
     None,
     Red,
@@ -23,6 +23,27 @@ pub enum ActivatingColor {
     Pink,
 }
 
+pub const COLORS: [ActivatingColor; 7] = [
+    ActivatingColor::None,
+    ActivatingColor::Red,
+    ActivatingColor::Blue,
+    ActivatingColor::Green,
+    ActivatingColor::Yellow,
+    ActivatingColor::Cyan,
+    ActivatingColor::Pink,
+];
+
+// Names used in config files.
+pub const COLOR_NAMES: [(ActivatingColor, &str); 7] = [
+    (ActivatingColor::None, "none"),
+    (ActivatingColor::Red, "red"),
+    (ActivatingColor::Blue, "blue"),
+    (ActivatingColor::Green, "green"),
+    (ActivatingColor::Yellow, "yellow"),
+    (ActivatingColor::Cyan, "cyan"),
+    (ActivatingColor::Pink, "pink"),
+];
+
 impl ToString for ActivatingColor {
     fn to_string(&self) -> String {
         match self {
@@ -51,7 +72,7 @@ impl Into<Color> for ActivatingColor {
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Layer<O = (), F = (), W = (), C = ()> {
     Object(O),
     Floor(F),
@@ -61,19 +82,4 @@ pub enum Layer<O = (), F = (), W = (), C = ()> {
 
 pub type LayerData = Layer<Object, Floor, WallData, Collectible>;
 pub type LayerContent = Layer<Object, Floor, Wall, Collectible>;
-
-impl LayerContent {
-    pub fn new(data: LayerData, orientation: WallOrientation) -> LayerContent {
-        match data {
-            LayerData::Floor(d) => LayerContent::Floor(d),
-            LayerData::Object(d) => LayerContent::Object(d),
-            LayerData::Collectible(d) => LayerContent::Collectible(d),
-
-            LayerData::Wall(d) => {
-                let mut wall = Wall::new();
-                wall.merge_data(d, orientation);
-                LayerContent::Wall(wall)
-            }
-        }
-    }
-}
+pub type LayerSlot = Layer<Option<Object>, Option<Floor>, Option<Wall>, Option<Collectible>>;
@@ -1,6 +1,6 @@
 use super::ActivatingColor;
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ObjectType {
     Player,
     Box,
@@ -8,7 +8,7 @@ pub enum ObjectType {
 }
 
 #[non_exhaustive]
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Object {
     pub object_type: ObjectType,
     pub color: ActivatingColor,
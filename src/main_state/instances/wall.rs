@@ -1,12 +1,12 @@
 use super::ActivatingColor;
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum WallType {
     Normal,
 }
 
 #[non_exhaustive]
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct WallData {
     pub wall_type: WallType,
     pub color: ActivatingColor,
@@ -39,13 +39,23 @@ impl WallData {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum WallOrientation {
     Right,
     Down,
 }
 
+impl WallOrientation {
+    pub fn name(&self) -> &'static str {
+        match self {
+            WallOrientation::Right => "right",
+            WallOrientation::Down => "down",
+        }
+    }
+}
+
 #[non_exhaustive]
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Wall {
     pub down: Option<WallData>,
     pub right: Option<WallData>,
@@ -59,15 +69,6 @@ impl Wall {
         }
     }
 
-    pub fn merge(&mut self, other: Wall) {
-        if let Some(data) = other.right {
-            self.right = Some(data);
-        }
-        if let Some(data) = other.down {
-            self.down = Some(data);
-        }
-    }
-
     pub fn merge_data(&mut self, other: WallData, orientation: WallOrientation) {
         match orientation {
             WallOrientation::Right => self.right = Some(other),
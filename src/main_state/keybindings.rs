@@ -0,0 +1,585 @@
+use std::fmt;
+
+use ggez::{
+    input::keyboard::{KeyInput, KeyMods},
+    winit::event::VirtualKeyCode,
+    Context,
+};
+
+use super::{config, instances::ActivatingColor};
+
+const CONFIG_PATH: &str = "/keybindings.toml";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    LayerObject,
+    LayerFloor,
+    LayerWall,
+    LayerCollectible,
+
+    Item(usize),
+    Color(ActivatingColor),
+
+    ToggleInputDependent,
+    ToggleClosed,
+    Durability,
+    Pick,
+
+    Save,
+    Load,
+
+    Undo,
+    Redo,
+
+    Inspect,
+    InspectorPrevField,
+    InspectorNextField,
+    InspectorDecrease,
+    InspectorIncrease,
+
+    NewLevel,
+    NewTab,
+    CloseTab,
+    NextTab,
+    PrevTab,
+
+    Copy,
+    Cut,
+    Paste,
+    ClearSelection,
+
+    ToggleGrid,
+    ToggleLinks,
+    NextPalette,
+    ToggleGlyphs,
+
+    ToggleLayerHidden,
+    ToggleLayerLocked,
+    ToggleDimInactive,
+    DimOpacityDown,
+    DimOpacityUp,
+    SoloLayer,
+
+    ToggleMinimap,
+
+    Play,
+    PlayReplay,
+    ToggleStats,
+    ToggleAnalysis,
+    Generate,
+    Command,
+    Find,
+    NextMatch,
+    PrevMatch,
+}
+
+const ACTIONS: [(&str, Action); 63] = [
+    ("layer_object", Action::LayerObject),
+    ("layer_floor", Action::LayerFloor),
+    ("layer_wall", Action::LayerWall),
+    ("layer_collectible", Action::LayerCollectible),
+    ("item_1", Action::Item(0)),
+    ("item_2", Action::Item(1)),
+    ("item_3", Action::Item(2)),
+    ("item_4", Action::Item(3)),
+    ("item_5", Action::Item(4)),
+    ("item_6", Action::Item(5)),
+    ("item_7", Action::Item(6)),
+    ("item_8", Action::Item(7)),
+    ("item_9", Action::Item(8)),
+    ("item_10", Action::Item(9)),
+    ("color_none", Action::Color(ActivatingColor::None)),
+    ("color_red", Action::Color(ActivatingColor::Red)),
+    ("color_blue", Action::Color(ActivatingColor::Blue)),
+    ("color_green", Action::Color(ActivatingColor::Green)),
+    ("color_yellow", Action::Color(ActivatingColor::Yellow)),
+    ("color_cyan", Action::Color(ActivatingColor::Cyan)),
+    ("color_pink", Action::Color(ActivatingColor::Pink)),
+    ("toggle_input_dependent", Action::ToggleInputDependent),
+    ("toggle_closed", Action::ToggleClosed),
+    ("durability", Action::Durability),
+    ("pick", Action::Pick),
+    ("save", Action::Save),
+    ("load", Action::Load),
+    ("undo", Action::Undo),
+    ("redo", Action::Redo),
+    ("inspect", Action::Inspect),
+    ("inspector_prev_field", Action::InspectorPrevField),
+    ("inspector_next_field", Action::InspectorNextField),
+    ("inspector_decrease", Action::InspectorDecrease),
+    ("inspector_increase", Action::InspectorIncrease),
+    ("new_level", Action::NewLevel),
+    ("new_tab", Action::NewTab),
+    ("close_tab", Action::CloseTab),
+    ("next_tab", Action::NextTab),
+    ("prev_tab", Action::PrevTab),
+    ("copy", Action::Copy),
+    ("cut", Action::Cut),
+    ("paste", Action::Paste),
+    ("clear_selection", Action::ClearSelection),
+    ("toggle_grid", Action::ToggleGrid),
+    ("toggle_links", Action::ToggleLinks),
+    ("next_palette", Action::NextPalette),
+    ("toggle_glyphs", Action::ToggleGlyphs),
+    ("toggle_layer_hidden", Action::ToggleLayerHidden),
+    ("toggle_layer_locked", Action::ToggleLayerLocked),
+    ("toggle_dim_inactive", Action::ToggleDimInactive),
+    ("dim_opacity_down", Action::DimOpacityDown),
+    ("dim_opacity_up", Action::DimOpacityUp),
+    ("solo_layer", Action::SoloLayer),
+    ("toggle_minimap", Action::ToggleMinimap),
+    ("play", Action::Play),
+    ("play_replay", Action::PlayReplay),
+    ("toggle_stats", Action::ToggleStats),
+    ("toggle_analysis", Action::ToggleAnalysis),
+    ("generate", Action::Generate),
+    ("command", Action::Command),
+    ("find", Action::Find),
+    ("next_match", Action::NextMatch),
+    ("prev_match", Action::PrevMatch),
+];
+
+const DEFAULT_BINDINGS: [(&str, &str); 64] = [
+    ("layer_object", "1"),
+    ("layer_floor", "2"),
+    ("layer_wall", "3"),
+    ("layer_collectible", "4"),
+    ("item_1", "Q"),
+    ("item_2", "W"),
+    ("item_3", "E"),
+    ("item_4", "R"),
+    ("item_5", "T"),
+    ("item_6", "Y"),
+    ("item_7", "U"),
+    ("item_8", "I"),
+    ("item_9", "O"),
+    ("item_10", "P"),
+    ("color_none", "A"),
+    ("color_red", "S"),
+    ("color_blue", "D"),
+    ("color_green", "F"),
+    ("color_yellow", "G"),
+    ("color_cyan", "H"),
+    ("color_pink", "J"),
+    ("toggle_input_dependent", "N"),
+    ("toggle_closed", "M"),
+    ("durability", "B"),
+    ("pick", "K"),
+    ("save", "Ctrl+S"),
+    ("load", "Ctrl+O"),
+    ("undo", "Ctrl+Z"),
+    ("redo", "Ctrl+Y"),
+    ("redo", "Ctrl+Shift+Z"),
+    ("inspect", "L"),
+    ("inspector_prev_field", "Up"),
+    ("inspector_next_field", "Down"),
+    ("inspector_decrease", "Left"),
+    ("inspector_increase", "Right"),
+    ("new_level", "Ctrl+N"),
+    ("new_tab", "Ctrl+T"),
+    ("close_tab", "Ctrl+W"),
+    ("next_tab", "Ctrl+Tab"),
+    ("prev_tab", "Ctrl+Shift+Tab"),
+    ("copy", "Ctrl+C"),
+    ("cut", "Ctrl+X"),
+    ("paste", "Ctrl+V"),
+    ("clear_selection", "Escape"),
+    ("toggle_grid", "Ctrl+G"),
+    ("toggle_links", "Ctrl+L"),
+    ("next_palette", "Ctrl+P"),
+    ("toggle_glyphs", "Ctrl+K"),
+    ("toggle_layer_hidden", "Alt+H"),
+    ("toggle_layer_locked", "Alt+L"),
+    ("toggle_dim_inactive", "Alt+D"),
+    ("dim_opacity_down", "Alt+Minus"),
+    ("dim_opacity_up", "Alt+Equals"),
+    ("solo_layer", "Alt+S"),
+    ("toggle_minimap", "Ctrl+M"),
+    ("play", "F5"),
+    ("play_replay", "F6"),
+    ("toggle_stats", "Ctrl+I"),
+    ("toggle_analysis", "Ctrl+D"),
+    ("generate", "Ctrl+Shift+N"),
+    ("command", "Ctrl+E"),
+    ("find", "Ctrl+F"),
+    ("next_match", "F3"),
+    ("prev_match", "Shift+F3"),
+];
+
+const KEY_NAMES: [(&str, VirtualKeyCode); 72] = [
+    ("1", VirtualKeyCode::Key1),
+    ("2", VirtualKeyCode::Key2),
+    ("3", VirtualKeyCode::Key3),
+    ("4", VirtualKeyCode::Key4),
+    ("5", VirtualKeyCode::Key5),
+    ("6", VirtualKeyCode::Key6),
+    ("7", VirtualKeyCode::Key7),
+    ("8", VirtualKeyCode::Key8),
+    ("9", VirtualKeyCode::Key9),
+    ("0", VirtualKeyCode::Key0),
+    ("A", VirtualKeyCode::A),
+    ("B", VirtualKeyCode::B),
+    ("C", VirtualKeyCode::C),
+    ("D", VirtualKeyCode::D),
+    ("E", VirtualKeyCode::E),
+    ("F", VirtualKeyCode::F),
+    ("G", VirtualKeyCode::G),
+    ("H", VirtualKeyCode::H),
+    ("I", VirtualKeyCode::I),
+    ("J", VirtualKeyCode::J),
+    ("K", VirtualKeyCode::K),
+    ("L", VirtualKeyCode::L),
+    ("M", VirtualKeyCode::M),
+    ("N", VirtualKeyCode::N),
+    ("O", VirtualKeyCode::O),
+    ("P", VirtualKeyCode::P),
+    ("Q", VirtualKeyCode::Q),
+    ("R", VirtualKeyCode::R),
+    ("S", VirtualKeyCode::S),
+    ("T", VirtualKeyCode::T),
+    ("U", VirtualKeyCode::U),
+    ("V", VirtualKeyCode::V),
+    ("W", VirtualKeyCode::W),
+    ("X", VirtualKeyCode::X),
+    ("Y", VirtualKeyCode::Y),
+    ("Z", VirtualKeyCode::Z),
+    ("F1", VirtualKeyCode::F1),
+    ("F2", VirtualKeyCode::F2),
+    ("F3", VirtualKeyCode::F3),
+    ("F4", VirtualKeyCode::F4),
+    ("F5", VirtualKeyCode::F5),
+    ("F6", VirtualKeyCode::F6),
+    ("F7", VirtualKeyCode::F7),
+    ("F8", VirtualKeyCode::F8),
+    ("F9", VirtualKeyCode::F9),
+    ("F10", VirtualKeyCode::F10),
+    ("F11", VirtualKeyCode::F11),
+    ("F12", VirtualKeyCode::F12),
+    ("Escape", VirtualKeyCode::Escape),
+    ("Enter", VirtualKeyCode::Return),
+    ("Space", VirtualKeyCode::Space),
+    ("Tab", VirtualKeyCode::Tab),
+    ("Backspace", VirtualKeyCode::Back),
+    ("Insert", VirtualKeyCode::Insert),
+    ("Delete", VirtualKeyCode::Delete),
+    ("Home", VirtualKeyCode::Home),
+    ("End", VirtualKeyCode::End),
+    ("PageUp", VirtualKeyCode::PageUp),
+    ("PageDown", VirtualKeyCode::PageDown),
+    ("Left", VirtualKeyCode::Left),
+    ("Right", VirtualKeyCode::Right),
+    ("Up", VirtualKeyCode::Up),
+    ("Down", VirtualKeyCode::Down),
+    ("Minus", VirtualKeyCode::Minus),
+    ("Equals", VirtualKeyCode::Equals),
+    ("Comma", VirtualKeyCode::Comma),
+    ("Period", VirtualKeyCode::Period),
+    ("Slash", VirtualKeyCode::Slash),
+    ("Semicolon", VirtualKeyCode::Semicolon),
+    ("Apostrophe", VirtualKeyCode::Apostrophe),
+    ("LBracket", VirtualKeyCode::LBracket),
+    ("RBracket", VirtualKeyCode::RBracket),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Key {
+    Code(VirtualKeyCode),
+    Scan(u32),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyBinding {
+    key: Key,
+    mods: KeyMods,
+}
+
+impl KeyBinding {
+    // Accepts chords like `Ctrl+Shift+S`. `#30` binds the raw scancode 30,
+    // which stays on the same physical key regardless of the keyboard layout.
+    pub fn parse(chord: &str) -> Result<KeyBinding, String> {
+        let mut mods = KeyMods::NONE;
+        let mut key = None;
+
+        for part in chord.split('+').map(str::trim) {
+            match part.to_lowercase().as_str() {
+                "ctrl" | "control" => mods |= KeyMods::CTRL,
+                "shift" => mods |= KeyMods::SHIFT,
+                "alt" => mods |= KeyMods::ALT,
+                "logo" | "super" | "cmd" => mods |= KeyMods::LOGO,
+
+                _ => {
+                    if key.is_some() {
+                        return Err(format!("`{}` has more than one key", chord));
+                    }
+                    key = Some(parse_key(part).ok_or(format!("unknown key `{}`", part))?);
+                }
+            }
+        }
+
+        match key {
+            Some(key) => Ok(KeyBinding { key, mods }),
+            None => Err(format!("`{}` has no key", chord)),
+        }
+    }
+
+    fn matches(&self, input: &KeyInput) -> bool {
+        if input.mods != self.mods {
+            return false;
+        }
+
+        match self.key {
+            Key::Code(code) => input.keycode == Some(code),
+            Key::Scan(scancode) => input.scancode == scancode,
+        }
+    }
+}
+
+impl fmt::Display for KeyBinding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut chord = String::new();
+
+        for (modifier, name) in [
+            (KeyMods::CTRL, "Ctrl+"),
+            (KeyMods::SHIFT, "Shift+"),
+            (KeyMods::ALT, "Alt+"),
+            (KeyMods::LOGO, "Logo+"),
+        ] {
+            if self.mods.contains(modifier) {
+                chord.push_str(name);
+            }
+        }
+
+        match self.key {
+            Key::Code(code) => match KEY_NAMES.iter().find(|(_, c)| *c == code) {
+                Some((name, _)) => chord.push_str(name),
+                None => chord.push_str(&format!("{:?}", code)),
+            },
+            Key::Scan(scancode) => chord.push_str(&format!("#{}", scancode)),
+        }
+
+        write!(f, "{}", chord)
+    }
+}
+
+fn parse_key(name: &str) -> Option<Key> {
+    if let Some(scancode) = name.strip_prefix('#') {
+        return scancode.parse().ok().map(Key::Scan);
+    }
+
+    let name = match name.to_lowercase().as_str() {
+        "return" => "enter",
+        "back" => "backspace",
+        "esc" => "escape",
+        _ => name,
+    }
+    .to_lowercase();
+
+    KEY_NAMES
+        .iter()
+        .find(|(key_name, _)| key_name.to_lowercase() == name)
+        .map(|(_, code)| Key::Code(*code))
+}
+
+fn parse_action(name: &str) -> Option<Action> {
+    ACTIONS
+        .iter()
+        .find(|(action_name, _)| *action_name == name)
+        .map(|(_, action)| *action)
+}
+
+fn action_name(action: Action) -> &'static str {
+    ACTIONS
+        .iter()
+        .find(|(_, a)| *a == action)
+        .map(|(name, _)| *name)
+        .unwrap_or("unknown")
+}
+
+#[derive(Debug, Clone)]
+pub struct KeyBindings {
+    bindings: Vec<(KeyBinding, Action)>,
+}
+
+impl KeyBindings {
+    pub fn new() -> KeyBindings {
+        let mut bindings = Vec::new();
+
+        for (action, chord) in DEFAULT_BINDINGS {
+            if let (Some(action), Ok(binding)) = (parse_action(action), KeyBinding::parse(chord)) {
+                bindings.push((binding, action));
+            }
+        }
+
+        KeyBindings { bindings }
+    }
+
+    // Reads `keybindings.toml` from the resources or user config directory.
+    // Every action listed in the `[bindings]` table loses its default keys,
+    // and configured keys take priority over the defaults of other actions.
+    pub fn load(ctx: &Context) -> (KeyBindings, Vec<String>) {
+        let mut problems = Vec::new();
+
+        let Some(config) = config::load(ctx, CONFIG_PATH, &mut problems) else {
+            return (KeyBindings::new(), problems);
+        };
+
+        let Some(table) = config.get("bindings").and_then(toml::Value::as_table) else {
+            problems.push(format!("{}: missing [bindings] table", CONFIG_PATH));
+            return (KeyBindings::new(), problems);
+        };
+
+        let mut bindings = Vec::new();
+        let mut configured = Vec::new();
+
+        for (name, value) in table {
+            let Some(action) = parse_action(name) else {
+                problems.push(format!("{}: unknown action `{}`", CONFIG_PATH, name));
+                continue;
+            };
+
+            let chords = match value {
+                toml::Value::String(chord) => vec![chord.as_str()],
+                toml::Value::Array(chords) => {
+                    chords.iter().filter_map(toml::Value::as_str).collect()
+                }
+                _ => {
+                    problems.push(format!(
+                        "{}: `{}` must be a key or a list of keys",
+                        CONFIG_PATH, name
+                    ));
+                    continue;
+                }
+            };
+
+            configured.push(action);
+
+            for chord in chords {
+                match KeyBinding::parse(chord) {
+                    Ok(binding) => bindings.push((binding, action)),
+                    Err(error) => problems.push(format!("{}: {}: {}", CONFIG_PATH, name, error)),
+                }
+            }
+        }
+
+        for (binding, action) in KeyBindings::new().bindings {
+            if !configured.contains(&action) {
+                bindings.push((binding, action));
+            }
+        }
+
+        let key_bindings = KeyBindings { bindings };
+        problems.extend(key_bindings.conflicts());
+
+        (key_bindings, problems)
+    }
+
+    fn conflicts(&self) -> Vec<String> {
+        let mut conflicts = Vec::new();
+
+        for (i, (binding, action)) in self.bindings.iter().enumerate() {
+            let Some((_, used)) = self.bindings[..i].iter().find(|(b, _)| b == binding) else {
+                continue;
+            };
+
+            if used != action {
+                conflicts.push(format!(
+                    "{} is bound to both {} and {}, using {}",
+                    binding,
+                    action_name(*used),
+                    action_name(*action),
+                    action_name(*used),
+                ));
+            }
+        }
+
+        conflicts
+    }
+
+    // Scancode bindings win over keycode ones, so a layout-independent key
+    // from the config is never shadowed by a default letter.
+    pub fn action(&self, input: &KeyInput) -> Option<Action> {
+        self.bindings
+            .iter()
+            .filter(|(binding, _)| matches!(binding.key, Key::Scan(_)))
+            .chain(
+                self.bindings
+                    .iter()
+                    .filter(|(binding, _)| matches!(binding.key, Key::Code(_))),
+            )
+            .find(|(binding, _)| binding.matches(input))
+            .map(|(_, action)| *action)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_modifiers_in_any_order() {
+        let binding = KeyBinding::parse("shift + CTRL+s").unwrap();
+
+        assert_eq!(binding.key, Key::Code(VirtualKeyCode::S));
+        assert_eq!(binding.mods, KeyMods::CTRL | KeyMods::SHIFT);
+        assert_eq!(binding.to_string(), "Ctrl+Shift+S");
+    }
+
+    #[test]
+    fn parses_scancodes_and_key_aliases() {
+        let binding = KeyBinding::parse("Alt+#30").unwrap();
+        assert_eq!(binding.key, Key::Scan(30));
+        assert_eq!(binding.mods, KeyMods::ALT);
+        assert_eq!(binding.to_string(), "Alt+#30");
+
+        assert_eq!(
+            KeyBinding::parse("esc").unwrap().key,
+            Key::Code(VirtualKeyCode::Escape)
+        );
+        assert_eq!(
+            KeyBinding::parse("Return").unwrap().key,
+            Key::Code(VirtualKeyCode::Return)
+        );
+    }
+
+    #[test]
+    fn rejects_bad_chords() {
+        assert_eq!(
+            KeyBinding::parse("Ctrl+Banana"),
+            Err(String::from("unknown key `Banana`"))
+        );
+        assert_eq!(
+            KeyBinding::parse("Ctrl+Shift"),
+            Err(String::from("`Ctrl+Shift` has no key"))
+        );
+        assert_eq!(
+            KeyBinding::parse("S+O"),
+            Err(String::from("`S+O` has more than one key"))
+        );
+        assert!(KeyBinding::parse("#thirty").is_err());
+    }
+
+    #[test]
+    fn defaults_parse_without_conflicts() {
+        let key_bindings = KeyBindings::new();
+
+        assert_eq!(key_bindings.bindings.len(), DEFAULT_BINDINGS.len());
+        assert_eq!(key_bindings.conflicts(), Vec::<String>::new());
+    }
+
+    #[test]
+    fn reports_conflicts_and_keeps_the_first_binding() {
+        let binding = KeyBinding::parse("Ctrl+S").unwrap();
+        let key_bindings = KeyBindings {
+            bindings: vec![
+                (binding, Action::Save),
+                (binding, Action::Load),
+                (binding, Action::Save),
+            ],
+        };
+
+        assert_eq!(
+            key_bindings.conflicts(),
+            ["Ctrl+S is bound to both save and load, using save"]
+        );
+    }
+}
@@ -0,0 +1,92 @@
+use std::collections::HashMap;
+
+use ggez::glam::IVec2;
+
+use super::{instances::Layer, level_data::LevelData};
+
+fn index(layer: Layer) -> usize {
+    match layer {
+        Layer::Object(()) => 0,
+        Layer::Floor(()) => 1,
+        Layer::Wall(()) => 2,
+        Layer::Collectible(()) => 3,
+    }
+}
+
+pub fn layer_name(layer: Layer) -> &'static str {
+    match layer {
+        Layer::Object(()) => "object",
+        Layer::Floor(()) => "floor",
+        Layer::Wall(()) => "wall",
+        Layer::Collectible(()) => "collectible",
+    }
+}
+
+// How each layer is shown and whether it can be edited. Shared by all tabs.
+#[derive(Debug, Default)]
+pub struct Layers {
+    hidden: [bool; 4],
+    locked: [bool; 4],
+    pub dim_inactive: bool,
+    // Opacity of the other layers while they are dimmed.
+    pub dim_opacity: f32,
+    pub solo: bool,
+}
+
+impl Layers {
+    const DIM_STEP: f32 = 0.1;
+
+    pub fn new() -> Layers {
+        Layers {
+            dim_opacity: 0.35,
+            ..Layers::default()
+        }
+    }
+
+    pub fn adjust_dim_opacity(&mut self, delta: f32) {
+        self.dim_opacity = (self.dim_opacity + delta * Self::DIM_STEP).clamp(0.05, 0.95);
+    }
+
+    pub fn toggle_hidden(&mut self, layer: Layer) {
+        self.hidden[index(layer)] = !self.hidden[index(layer)];
+    }
+
+    pub fn toggle_locked(&mut self, layer: Layer) {
+        self.locked[index(layer)] = !self.locked[index(layer)];
+    }
+
+    pub fn hidden(&self, layer: Layer) -> bool {
+        self.hidden[index(layer)]
+    }
+
+    pub fn locked(&self, layer: Layer) -> bool {
+        self.locked[index(layer)]
+    }
+
+    pub fn opacity(&self, layer: Layer, active: Layer) -> f32 {
+        if self.hidden(layer) || (self.solo && layer != active) {
+            0.0
+        } else if self.dim_inactive && layer != active {
+            self.dim_opacity
+        } else {
+            1.0
+        }
+    }
+
+    // The part of `level_data` that lies on unlocked layers.
+    pub fn editable(&self, level_data: LevelData) -> LevelData {
+        LevelData {
+            objects: self.keep(Layer::Object(()), level_data.objects),
+            walls: self.keep(Layer::Wall(()), level_data.walls),
+            floors: self.keep(Layer::Floor(()), level_data.floors),
+            collectibles: self.keep(Layer::Collectible(()), level_data.collectibles),
+        }
+    }
+
+    fn keep<T>(&self, layer: Layer, contents: HashMap<IVec2, T>) -> HashMap<IVec2, T> {
+        match self.locked(layer) {
+            true => HashMap::new(),
+            false => contents,
+        }
+    }
+}
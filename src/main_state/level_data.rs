@@ -2,7 +2,7 @@ use std::collections::HashMap;
 
 use ggez::{
     glam::IVec2,
-    graphics::{Canvas, DrawParam},
+    graphics::{Canvas, Color, DrawParam},
     mint::Point2,
     Context, GameResult,
 };
@@ -13,12 +13,12 @@ use super::{
         floor::Floor,
         object::Object,
         wall::{Wall, WallOrientation},
-        Layer, LayerContent, LayerData,
+        Layer, LayerContent, LayerData, LayerSlot,
     },
     resources::Resources,
 };
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone, PartialEq)]
 pub struct LevelData {
     pub objects: HashMap<IVec2, Object>,
     pub walls: HashMap<IVec2, Wall>,
@@ -27,6 +27,8 @@ pub struct LevelData {
 }
 
 impl LevelData {
+    const GHOST_OPACITY: f32 = 0.5;
+
     pub fn new() -> LevelData {
         LevelData::default()
     }
@@ -37,122 +39,89 @@ impl LevelData {
             y: (pos.y as f32) * 16.0,
         })
     }
-    //
-    // pub fn draw(&self, canvas: &mut Canvas, resources: &Resources) -> GameResult {
-    //     for (pos, floor) in &self.floors {
-    //         let draw_param = Self::gen_draw_param(&pos);
-    //         resources.draw_content(canvas, LayerContent::Floor(floor.clone()), draw_param)?;
-    //     }
-    //
-    //     for (pos, collectible) in &self.collectibles {
-    //         let draw_param = Self::gen_draw_param(&pos);
-    //         resources.draw_content(
-    //             canvas,
-    //             LayerContent::Collectible(collectible.clone()),
-    //             draw_param,
-    //         )?;
-    //     }
-    //
-    //     for (pos, object) in &self.objects {
-    //         let draw_param = Self::gen_draw_param(&pos);
-    //         resources.draw_content(canvas, LayerContent::Object(object.clone()), draw_param)?;
-    //     }
-    //
-    //     for (pos, wall) in &self.walls {
-    //         let draw_param = Self::gen_draw_param(&pos);
-    //         resources.draw_content(canvas, LayerContent::Wall(wall.clone()), draw_param)?;
-    //     }
-    //
-    //     Ok(())
-    // }
-    //
+
+    // `opacity` fades whole layers, layers with zero opacity are not drawn at all.
+    // `preview` is drawn see-through right after the layer it belongs to, so the
+    // ghost is covered by the same layers that would cover the real thing.
     pub fn draw_with(
         &self,
         ctx: &Context,
-        data_with: LayerContent,
-        pos_with: &IVec2,
+        preview: &LevelData,
         canvas: &mut Canvas,
         resources: &Resources,
+        opacity: impl Fn(Layer) -> f32,
     ) -> GameResult {
-        let mut consumed = false;
-
-        for (pos, floor) in &self.floors {
-            let mut content = floor.clone();
-            if pos == pos_with {
-                if let LayerContent::Floor(dt) = data_with.clone() {
-                    content = dt;
-                    consumed = true;
-                }
-            }
-            let draw_param = Self::gen_draw_param(&pos);
-            resources.draw_content(ctx, canvas, LayerContent::Floor(content), draw_param)?;
-        }
+        Self::draw_layer(
+            ctx,
+            canvas,
+            resources,
+            [&self.floors, &preview.floors],
+            LayerContent::Floor,
+            opacity(Layer::Floor(())),
+        )?;
+        Self::draw_layer(
+            ctx,
+            canvas,
+            resources,
+            [&self.collectibles, &preview.collectibles],
+            LayerContent::Collectible,
+            opacity(Layer::Collectible(())),
+        )?;
+        Self::draw_layer(
+            ctx,
+            canvas,
+            resources,
+            [&self.objects, &preview.objects],
+            LayerContent::Object,
+            opacity(Layer::Object(())),
+        )?;
+        Self::draw_layer(
+            ctx,
+            canvas,
+            resources,
+            [&self.walls, &preview.walls],
+            LayerContent::Wall,
+            opacity(Layer::Wall(())),
+        )?;
 
-        if !consumed {
-            if let LayerContent::Floor(_) = data_with {
-                let draw_param = Self::gen_draw_param(&pos_with);
-                resources.draw_content(ctx, canvas, data_with.clone(), draw_param)?;
-            }
-        }
-
-        for (pos, collectible) in &self.collectibles {
-            let mut content = collectible.clone();
-            if pos == pos_with {
-                if let LayerContent::Collectible(dt) = data_with.clone() {
-                    content = dt;
-                    consumed = true;
-                }
-            }
-            let draw_param = Self::gen_draw_param(&pos);
-            resources.draw_content(ctx, canvas, LayerContent::Collectible(content), draw_param)?;
-        }
+        Ok(())
+    }
 
-        if !consumed {
-            if let LayerContent::Collectible(_) = data_with {
-                let draw_param = Self::gen_draw_param(&pos_with);
-                resources.draw_content(ctx, canvas, data_with.clone(), draw_param)?;
-            }
+    fn draw_layer<T: Clone>(
+        ctx: &Context,
+        canvas: &mut Canvas,
+        resources: &Resources,
+        [contents, preview]: [&HashMap<IVec2, T>; 2],
+        wrap: fn(T) -> LayerContent,
+        opacity: f32,
+    ) -> GameResult {
+        if opacity <= 0.0 {
+            return Ok(());
         }
 
-        for (pos, object) in &self.objects {
-            let mut content = object.clone();
-            if pos == pos_with {
-                if let LayerContent::Object(dt) = data_with.clone() {
-                    content = dt;
-                    consumed = true;
-                }
-            }
-            let draw_param = Self::gen_draw_param(&pos);
-            resources.draw_content(ctx, canvas, LayerContent::Object(content), draw_param)?;
-        }
+        for (contents, opacity) in [
+            (contents, opacity),
+            (preview, opacity * Self::GHOST_OPACITY),
+        ] {
+            let color = Color::new(1.0, 1.0, 1.0, opacity);
 
-        if !consumed {
-            if let LayerContent::Object(_) = data_with {
-                let draw_param = Self::gen_draw_param(&pos_with);
-                resources.draw_content(ctx, canvas, data_with.clone(), draw_param)?;
+            for (pos, content) in contents {
+                let draw_param = Self::gen_draw_param(pos).color(color);
+                resources.draw_content(ctx, canvas, wrap(content.clone()), draw_param)?;
             }
         }
 
-        for (pos, wall) in &self.walls {
-            let mut content = wall.clone();
-            if pos == pos_with {
-                if let LayerContent::Wall(dt) = data_with.clone() {
-                    content.merge(dt);
-                    consumed = true;
-                }
-            }
-            let draw_param = Self::gen_draw_param(&pos);
-            resources.draw_content(ctx, canvas, LayerContent::Wall(content), draw_param)?;
-        }
+        Ok(())
+    }
 
-        if !consumed {
-            if let LayerContent::Wall(_) = data_with {
-                let draw_param = Self::gen_draw_param(&pos_with);
-                resources.draw_content(ctx, canvas, data_with.clone(), draw_param)?;
-            }
+    // The same contents moved by `offset`, e.g. a copied region placed at the cursor.
+    pub fn translated(&self, offset: IVec2) -> LevelData {
+        LevelData {
+            objects: Self::copy_layer(&self.objects, |_| true, -offset),
+            walls: Self::copy_layer(&self.walls, |_| true, -offset),
+            floors: Self::copy_layer(&self.floors, |_| true, -offset),
+            collectibles: Self::copy_layer(&self.collectibles, |_| true, -offset),
         }
-
-        Ok(())
     }
 
     pub fn insert(&mut self, pos: IVec2, data: LayerData, orientation: WallOrientation) {
@@ -182,6 +151,130 @@ impl LevelData {
         };
     }
 
+    // Every cell with anything on it, cells with several layers more than once.
+    pub fn positions(&self) -> impl Iterator<Item = &IVec2> {
+        self.objects
+            .keys()
+            .chain(self.walls.keys())
+            .chain(self.floors.keys())
+            .chain(self.collectibles.keys())
+    }
+
+    // Keeps only the cells `keep` accepts.
+    pub fn retain(&mut self, keep: impl Fn(&IVec2) -> bool) {
+        self.objects.retain(|pos, _| keep(pos));
+        self.walls.retain(|pos, _| keep(pos));
+        self.floors.retain(|pos, _| keep(pos));
+        self.collectibles.retain(|pos, _| keep(pos));
+    }
+
+    // The largest coordinates in use, which `save` writes into the header.
+    pub fn dimensions(&self) -> IVec2 {
+        self.positions()
+            .fold(IVec2::new(0, 0), |dimensions, pos| dimensions.max(*pos))
+    }
+
+    // The inclusive rectangle around every used cell, negative ones included.
+    pub fn extent(&self) -> (IVec2, IVec2) {
+        self.positions()
+            .fold((IVec2::new(0, 0), IVec2::new(0, 0)), |(min, max), pos| {
+                (min.min(*pos), max.max(*pos))
+            })
+    }
+
+    // Everything inside the inclusive rectangle, moved so that `min` becomes the origin.
+    pub fn copy_region(&self, min: IVec2, max: IVec2) -> LevelData {
+        let inside = |pos: &IVec2| pos.cmpge(min).all() && pos.cmple(max).all();
+
+        LevelData {
+            objects: Self::copy_layer(&self.objects, inside, min),
+            walls: Self::copy_layer(&self.walls, inside, min),
+            floors: Self::copy_layer(&self.floors, inside, min),
+            collectibles: Self::copy_layer(&self.collectibles, inside, min),
+        }
+    }
+
+    fn copy_layer<T: Clone>(
+        layer: &HashMap<IVec2, T>,
+        inside: impl Fn(&IVec2) -> bool,
+        origin: IVec2,
+    ) -> HashMap<IVec2, T> {
+        layer
+            .iter()
+            .filter(|(pos, _)| inside(pos))
+            .map(|(pos, content)| (*pos - origin, content.clone()))
+            .collect()
+    }
+
+    pub fn get(
+        &self,
+        pos: IVec2,
+        layer: Layer<(), (), (), ()>,
+        orientation: WallOrientation,
+    ) -> Option<LayerData> {
+        match layer {
+            Layer::Object(()) => self.objects.get(&pos).cloned().map(LayerData::Object),
+            Layer::Floor(()) => self.floors.get(&pos).cloned().map(LayerData::Floor),
+
+            Layer::Wall(()) => {
+                let wall = self.walls.get(&pos)?;
+                let wall_data = match orientation {
+                    WallOrientation::Right => wall.right.clone(),
+                    WallOrientation::Down => wall.down.clone(),
+                };
+
+                wall_data.map(LayerData::Wall)
+            }
+
+            Layer::Collectible(()) => self
+                .collectibles
+                .get(&pos)
+                .cloned()
+                .map(LayerData::Collectible),
+        }
+    }
+
+    pub fn slot(&self, pos: IVec2, layer: Layer<(), (), (), ()>) -> LayerSlot {
+        match layer {
+            Layer::Object(()) => LayerSlot::Object(self.objects.get(&pos).cloned()),
+            Layer::Floor(()) => LayerSlot::Floor(self.floors.get(&pos).cloned()),
+            Layer::Wall(()) => LayerSlot::Wall(self.walls.get(&pos).cloned()),
+            Layer::Collectible(()) => LayerSlot::Collectible(self.collectibles.get(&pos).cloned()),
+        }
+    }
+
+    pub fn set_slot(&mut self, pos: IVec2, slot: LayerSlot) {
+        match slot {
+            LayerSlot::Object(Some(object)) => {
+                self.objects.insert(pos, object);
+            }
+            LayerSlot::Object(None) => {
+                self.objects.remove(&pos);
+            }
+
+            LayerSlot::Floor(Some(floor)) => {
+                self.floors.insert(pos, floor);
+            }
+            LayerSlot::Floor(None) => {
+                self.floors.remove(&pos);
+            }
+
+            LayerSlot::Wall(Some(wall)) => {
+                self.walls.insert(pos, wall);
+            }
+            LayerSlot::Wall(None) => {
+                self.walls.remove(&pos);
+            }
+
+            LayerSlot::Collectible(Some(collectible)) => {
+                self.collectibles.insert(pos, collectible);
+            }
+            LayerSlot::Collectible(None) => {
+                self.collectibles.remove(&pos);
+            }
+        }
+    }
+
     pub fn remove(
         &mut self,
         pos: IVec2,
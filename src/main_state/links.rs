@@ -0,0 +1,155 @@
+use ggez::{
+    glam::IVec2,
+    graphics::{Canvas, Color, DrawMode, DrawParam, Mesh, MeshBuilder, Rect},
+    mint::Point2,
+    Context, GameResult,
+};
+
+use super::{
+    instances::{floor::FloorType, ActivatingColor},
+    level_data::LevelData,
+    palette::Palettes,
+    MainState,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Button,
+    Teleport,
+    Other,
+}
+
+// Anything with an activating color, with the point its link lines start from.
+struct Member {
+    cell: IVec2,
+    point: Point2<f32>,
+    color: ActivatingColor,
+    kind: Kind,
+}
+
+pub struct Links {
+    pub visible: bool,
+}
+
+impl Links {
+    pub fn new() -> Links {
+        Links { visible: false }
+    }
+
+    pub fn toggle(&mut self) {
+        self.visible = !self.visible;
+    }
+
+    fn members(level_data: &LevelData) -> Vec<Member> {
+        let size = MainState::CELL_SIZE as f32;
+        let point = |pos: &IVec2, x: f32, y: f32| Point2 {
+            x: (pos.x as f32 + x) * size,
+            y: (pos.y as f32 + y) * size,
+        };
+
+        let mut members = Vec::new();
+        let mut add = |cell: &IVec2, point, color, kind| {
+            if color != ActivatingColor::None {
+                members.push(Member {
+                    cell: *cell,
+                    point,
+                    color,
+                    kind,
+                });
+            }
+        };
+
+        for (pos, floor) in &level_data.floors {
+            let kind = match floor.floor_type {
+                FloorType::Button => Kind::Button,
+                FloorType::Teleport => Kind::Teleport,
+                FloorType::Normal => Kind::Other,
+            };
+            add(pos, point(pos, 0.5, 0.5), floor.color, kind);
+        }
+        for (pos, object) in &level_data.objects {
+            add(pos, point(pos, 0.5, 0.5), object.color, Kind::Other);
+        }
+        for (pos, collectible) in &level_data.collectibles {
+            add(pos, point(pos, 0.5, 0.5), collectible.color, Kind::Other);
+        }
+        for (pos, wall) in &level_data.walls {
+            if let Some(wall_data) = &wall.right {
+                add(pos, point(pos, 1.0, 0.5), wall_data.color, Kind::Other);
+            }
+            if let Some(wall_data) = &wall.down {
+                add(pos, point(pos, 0.5, 1.0), wall_data.color, Kind::Other);
+            }
+        }
+
+        members
+    }
+
+    // Lines from every button to whatever shares its color, drawn once between two
+    // buttons, and between teleports of the same color. The groups of the colors
+    // under the cursor are outlined.
+    pub fn draw(
+        &self,
+        ctx: &Context,
+        canvas: &mut Canvas,
+        screen_rect: Rect,
+        level_data: &LevelData,
+        palettes: &Palettes,
+        hovered: IVec2,
+    ) -> GameResult {
+        if !self.visible {
+            return Ok(());
+        }
+
+        let size = MainState::CELL_SIZE as f32;
+        let pixel = screen_rect.w / ctx.gfx.size().0;
+        let members = Self::members(level_data);
+        let mut builder = MeshBuilder::new();
+        let mut empty = true;
+
+        for (i, from) in members.iter().enumerate() {
+            for (j, to) in members.iter().enumerate() {
+                let linked = match (from.kind, to.kind) {
+                    (Kind::Button, Kind::Button) | (Kind::Teleport, Kind::Teleport) => i < j,
+                    (Kind::Button, _) => true,
+                    _ => false,
+                };
+
+                if linked && from.color == to.color && from.point != to.point {
+                    builder.line(
+                        &[from.point, to.point],
+                        pixel * 1.5,
+                        palettes.color(from.color),
+                    )?;
+                    empty = false;
+                }
+            }
+        }
+
+        let hovered_colors = members
+            .iter()
+            .filter(|member| member.cell == hovered)
+            .map(|member| member.color)
+            .collect::<Vec<_>>();
+
+        for member in &members {
+            if hovered_colors.contains(&member.color) {
+                let rect = Rect::new(
+                    member.cell.x as f32 * size,
+                    member.cell.y as f32 * size,
+                    size,
+                    size,
+                );
+                builder.rectangle(DrawMode::stroke(pixel * 3.0), rect, Color::WHITE)?;
+                empty = false;
+            }
+        }
+
+        // Meshes without vertices can't be created.
+        if !empty {
+            canvas.draw(&Mesh::from_data(ctx, builder.build()), DrawParam::default());
+        }
+
+        Ok(())
+    }
+}
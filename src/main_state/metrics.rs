@@ -0,0 +1,313 @@
+use std::{collections::BTreeMap, fs, path::PathBuf};
+
+use ggez::{
+    glam::IVec2,
+    graphics::{Canvas, Color, DrawMode, DrawParam, Mesh, Rect, Text},
+    mint::Point2,
+    Context, GameError, GameResult,
+};
+
+use super::{
+    document::Document,
+    game::Game,
+    instances::Layer,
+    layers::layer_name,
+    level_data::LevelData,
+    serialization,
+    solver::{self, Search},
+};
+
+// Statistics of a level, to compare levels by more than a feeling of how hard they are.
+#[derive(Debug, Clone)]
+pub struct Metrics {
+    // Number of things of each layer, type and color, like `floor Button Red`.
+    pub counts: BTreeMap<String, usize>,
+    pub size: IVec2,
+    pub input_dependent_walls: usize,
+    // Why the level can't be played, if it can't.
+    pub search: Result<Search, String>,
+    // How many times the shortest solution presses a button.
+    pub button_presses: Option<usize>,
+}
+
+impl Metrics {
+    // The editor uses a lower limit to stay responsive, the `stats` command a higher one.
+    pub const EDITOR_LIMIT: usize = 10_000;
+    pub const COMMAND_LIMIT: usize = 2_000_000;
+
+    pub fn new(level_data: &LevelData, limit: usize) -> Metrics {
+        let mut counts = BTreeMap::new();
+        let mut count = |layer: Layer, name: String| {
+            *counts
+                .entry(format!("{} {}", layer_name(layer), name))
+                .or_insert(0) += 1;
+        };
+
+        for object in level_data.objects.values() {
+            count(
+                Layer::Object(()),
+                format!("{:?} {:?}", object.object_type, object.color),
+            );
+        }
+        for floor in level_data.floors.values() {
+            count(
+                Layer::Floor(()),
+                format!("{:?} {:?}", floor.floor_type, floor.color),
+            );
+        }
+        let mut input_dependent_walls = 0;
+        for wall in level_data.walls.values() {
+            for wall_data in [&wall.right, &wall.down].into_iter().flatten() {
+                count(
+                    Layer::Wall(()),
+                    format!("{:?} {:?}", wall_data.wall_type, wall_data.color),
+                );
+                if wall_data.input_dependent {
+                    input_dependent_walls += 1;
+                }
+            }
+        }
+        for collectible in level_data.collectibles.values() {
+            count(
+                Layer::Collectible(()),
+                format!("{:?} {:?}", collectible.collectible_type, collectible.color),
+            );
+        }
+
+        // Only the cells in use count, an empty level has no size at all.
+        let bounds = level_data
+            .positions()
+            .fold(None, |bounds, pos| match bounds {
+                Some((min, max)) => Some((pos.min(min), pos.max(max))),
+                None => Some((*pos, *pos)),
+            });
+        let size = bounds.map_or(IVec2::ZERO, |(min, max)| max - min + IVec2::ONE);
+
+        let game = Game::new(level_data);
+        let search = game
+            .as_ref()
+            .map(|game| solver::search(game, limit))
+            .map_err(Clone::clone);
+
+        // A press is a button that wasn't pressed one move earlier.
+        let button_presses = match (&game, &search) {
+            (Ok(game), Ok(search)) if search.solution.is_some() => Some(
+                search
+                    .path
+                    .windows(2)
+                    .map(|states| {
+                        let before = game.pressed_buttons(&states[0]);
+                        game.pressed_buttons(&states[1])
+                            .iter()
+                            .filter(|pos| !before.contains(pos))
+                            .count()
+                    })
+                    .sum(),
+            ),
+            _ => None,
+        };
+
+        Metrics {
+            counts,
+            size,
+            input_dependent_walls,
+            search,
+            button_presses,
+        }
+    }
+
+    pub fn solution_length(&self) -> Option<usize> {
+        let search = self.search.as_ref().ok()?;
+
+        search.solution.as_ref().map(Vec::len)
+    }
+
+    pub fn lines(&self) -> Vec<String> {
+        let mut lines = vec![
+            format!(
+                "size: {}x{} ({} cells)",
+                self.size.x,
+                self.size.y,
+                self.size.x * self.size.y
+            ),
+            format!("input dependent walls: {}", self.input_dependent_walls),
+        ];
+        for (name, count) in &self.counts {
+            lines.push(format!("{}: {}", name, count));
+        }
+
+        match &self.search {
+            Ok(search) => {
+                let more = if search.complete {
+                    ""
+                } else {
+                    "+ (limit reached)"
+                };
+                lines.push(format!("reachable states: {}{}", search.states, more));
+
+                lines.push(match self.solution_length() {
+                    Some(length) => format!("shortest solution: {} moves", length),
+                    None if search.complete => String::from("shortest solution: unsolvable"),
+                    None => String::from("shortest solution: not found"),
+                });
+
+                if let Some(dead_ends) = search.dead_ends {
+                    lines.push(format!(
+                        "dead end states: {} ({:.0}%)",
+                        dead_ends,
+                        dead_ends as f32 / search.states as f32 * 100.0
+                    ));
+                }
+                if let Some(button_presses) = self.button_presses {
+                    lines.push(format!("button presses: {}", button_presses));
+                }
+            }
+            Err(error) => lines.push(format!("not playable: {}", error)),
+        }
+
+        lines
+    }
+}
+
+// Panel with the metrics of the current level. They are only computed again
+// when the level has changed and no mouse button is held, so painting stays smooth.
+#[derive(Debug, Default)]
+pub struct StatsPanel {
+    pub visible: bool,
+    // The metrics with the document id and revision they were computed for.
+    computed: Option<((u64, u64), Metrics)>,
+}
+
+impl StatsPanel {
+    pub fn new() -> StatsPanel {
+        StatsPanel::default()
+    }
+
+    pub fn toggle(&mut self) {
+        self.visible = !self.visible;
+    }
+
+    pub fn update(&mut self, document: &Document, painting: bool) {
+        if !self.visible || painting {
+            return;
+        }
+
+        let key = (document.id, document.revision);
+        if self
+            .computed
+            .as_ref()
+            .is_none_or(|(computed, _)| *computed != key)
+        {
+            let metrics = Metrics::new(&document.level_data, Metrics::EDITOR_LIMIT);
+            self.computed = Some((key, metrics));
+        }
+    }
+
+    // Draws in screen coordinates, below the tabs.
+    pub fn draw(&self, ctx: &Context, canvas: &mut Canvas) -> GameResult {
+        let Some((_, metrics)) = self.computed.as_ref().filter(|_| self.visible) else {
+            return Ok(());
+        };
+
+        let text = Text::new(metrics.lines().join("\n"));
+        let bounds = text.measure(ctx)?;
+
+        let panel = Rect::new(0.0, 20.0, bounds.x + 16.0, bounds.y + 16.0);
+        let background =
+            Mesh::new_rectangle(ctx, DrawMode::fill(), panel, Color::new(0.0, 0.0, 0.0, 0.8))?;
+
+        canvas.draw(&background, DrawParam::default());
+        canvas.draw(
+            &text,
+            DrawParam::default().dest(Point2 {
+                x: panel.x + 8.0,
+                y: panel.y + 8.0,
+            }),
+        );
+
+        Ok(())
+    }
+}
+
+// Prints the metrics of every level, levels inside directories included, and with
+// more than one level a list ordered from the shortest solution to the longest.
+pub fn print_files(levels: &[PathBuf]) -> GameResult {
+    let mut files = Vec::new();
+    for level in levels {
+        match level.is_dir() {
+            true => files.extend(serialization::level_files(level)?),
+            false => files.push(level.clone()),
+        }
+    }
+
+    let mut failures = Vec::new();
+    let mut ordered = Vec::new();
+
+    for level in files {
+        let contents = match fs::read_to_string(&level) {
+            Ok(contents) => contents,
+            Err(error) => {
+                failures.push(format!("{}: {}", level.display(), error));
+                continue;
+            }
+        };
+
+        let metrics = Metrics::new(&serialization::from_str(&contents), Metrics::COMMAND_LIMIT);
+
+        println!("{}", level.display());
+        for line in metrics.lines() {
+            println!("    {}", line);
+        }
+
+        let states = metrics.search.as_ref().map_or(0, |search| search.states);
+        ordered.push((metrics.solution_length(), states, level));
+    }
+
+    if ordered.len() > 1 {
+        // Unsolvable levels go last.
+        ordered.sort_by_key(|(length, states, _)| (length.is_none(), *length, *states));
+
+        println!("by difficulty:");
+        for (length, states, level) in ordered {
+            let length = length.map_or(String::from("-"), |length| length.to_string());
+            println!(
+                "    {:>5} moves {:>8} states  {}",
+                length,
+                states,
+                level.display()
+            );
+        }
+    }
+
+    match failures.is_empty() {
+        true => Ok(()),
+        false => Err(GameError::CustomError(failures.join("\n"))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn measures_the_button_wall_level() {
+        let level_data = serialization::from_str(include_str!("../../tests/fixtures/button_wall"));
+        let metrics = Metrics::new(&level_data, Metrics::COMMAND_LIMIT);
+
+        assert_eq!(metrics.size, IVec2::new(5, 3));
+        assert_eq!(metrics.input_dependent_walls, 1);
+        assert_eq!(metrics.counts["floor Normal None"], 6);
+        assert_eq!(metrics.counts["floor Button Red"], 1);
+        assert_eq!(metrics.solution_length(), Some(6));
+        assert_eq!(metrics.button_presses, Some(1));
+    }
+
+    #[test]
+    fn measures_only_the_cells_in_use() {
+        let level_data = serialization::from_str("0,0\nfloor 3,4  dur:-1 c:n\n");
+        assert_eq!(Metrics::new(&level_data, 10).size, IVec2::new(1, 1));
+
+        let level_data = LevelData::new();
+        assert_eq!(Metrics::new(&level_data, 10).size, IVec2::ZERO);
+    }
+}
@@ -0,0 +1,221 @@
+use ggez::{
+    glam::IVec2,
+    graphics::{Canvas, Color, DrawMode, DrawParam, Image, ImageFormat, Mesh, Rect},
+    input::mouse::MouseButton,
+    mint::Point2,
+    Context, GameResult,
+};
+
+use super::{document::Document, level_data::LevelData, MainState};
+
+const FLOOR_COLOR: [u8; 4] = [90, 90, 90, 255];
+const WALL_COLOR: [u8; 4] = [230, 230, 230, 255];
+const COLLECTIBLE_COLOR: [u8; 4] = [240, 200, 40, 255];
+const OBJECT_COLOR: [u8; 4] = [240, 120, 40, 255];
+
+// Where the minimap is on the screen and which cells it covers.
+struct Layout {
+    panel: Rect,
+    min: IVec2,
+    size: IVec2,
+    // Screen pixels per cell.
+    scale: f32,
+    // Cells per image pixel, more than one for levels too large to fit otherwise.
+    step: i32,
+}
+
+pub struct Minimap {
+    pub visible: bool,
+    dragging: bool,
+    // The image with the document id and revision it was made for.
+    image: Option<((u64, u64), Image)>,
+}
+
+impl Minimap {
+    const MAX_WIDTH: f32 = 160.0;
+    const MAX_HEIGHT: f32 = 120.0;
+    const MAX_SCALE: f32 = 8.0;
+    const MARGIN: i32 = 2;
+
+    pub fn new() -> Minimap {
+        Minimap {
+            visible: true,
+            dragging: false,
+            image: None,
+        }
+    }
+
+    pub fn toggle(&mut self) {
+        self.visible = !self.visible;
+        self.dragging = false;
+    }
+
+    // The level is rendered at one pixel per cell and then scaled to fit into
+    // the bottom right corner, above the status bar. Levels spread too far apart
+    // for that get several cells per pixel, so the image never grows past the panel.
+    fn layout(ctx: &Context, level_data: &LevelData) -> Layout {
+        let (min, max) = level_data.extent();
+        let min = min - IVec2::splat(Self::MARGIN);
+        let size = max - min + IVec2::splat(Self::MARGIN + 1);
+
+        let scale = (Self::MAX_WIDTH / size.x as f32)
+            .min(Self::MAX_HEIGHT / size.y as f32)
+            .min(Self::MAX_SCALE);
+
+        let step = (1.0 / scale).ceil().max(1.0) as i32;
+
+        let (width, height) = ctx.gfx.size();
+        let panel_size = (size.x as f32 * scale, size.y as f32 * scale);
+        let panel = Rect::new(
+            width - panel_size.0 - 8.0,
+            height - panel_size.1 - 28.0,
+            panel_size.0,
+            panel_size.1,
+        );
+
+        Layout {
+            panel,
+            min,
+            size,
+            scale,
+            step,
+        }
+    }
+
+    // Size of the image in pixels.
+    fn pixel_size(layout: &Layout) -> IVec2 {
+        (layout.size + IVec2::splat(layout.step - 1)) / layout.step
+    }
+
+    // Moves the camera while the minimap is clicked or dragged. Returns whether
+    // the mouse belongs to the minimap, so clicks don't also edit the level.
+    pub fn update(
+        &mut self,
+        ctx: &Context,
+        level_data: &LevelData,
+        screen_rect: &mut Rect,
+    ) -> bool {
+        if !self.visible {
+            return false;
+        }
+
+        let layout = Self::layout(ctx, level_data);
+        let mouse_pos = ctx.mouse.position();
+
+        if ctx.mouse.button_just_pressed(MouseButton::Left) && layout.panel.contains(mouse_pos) {
+            self.dragging = true;
+        }
+        if !ctx.mouse.button_pressed(MouseButton::Left) {
+            self.dragging = false;
+        }
+
+        if self.dragging {
+            let size = MainState::CELL_SIZE as f32;
+            let cell_x = layout.min.x as f32 + (mouse_pos.x - layout.panel.x) / layout.scale;
+            let cell_y = layout.min.y as f32 + (mouse_pos.y - layout.panel.y) / layout.scale;
+
+            screen_rect.x = cell_x * size - screen_rect.w / 2.0;
+            screen_rect.y = cell_y * size - screen_rect.h / 2.0;
+        }
+
+        self.dragging
+    }
+
+    // Whether the mouse is over the minimap or dragging it.
+    pub fn hovered(&self, ctx: &Context, level_data: &LevelData) -> bool {
+        self.visible
+            && (self.dragging
+                || Self::layout(ctx, level_data)
+                    .panel
+                    .contains(ctx.mouse.position()))
+    }
+
+    fn pixels(level_data: &LevelData, layout: &Layout) -> Vec<u8> {
+        let size = Self::pixel_size(layout);
+        let mut pixels = vec![0; (size.x * size.y * 4) as usize];
+        let mut paint = |pos: &IVec2, color: [u8; 4]| {
+            let pos = (*pos - layout.min) / layout.step;
+            let index = ((pos.y * size.x + pos.x) * 4) as usize;
+            pixels[index..index + 4].copy_from_slice(&color);
+        };
+
+        for pos in level_data.floors.keys() {
+            paint(pos, FLOOR_COLOR);
+        }
+        for pos in level_data.walls.keys() {
+            paint(pos, WALL_COLOR);
+        }
+        for pos in level_data.collectibles.keys() {
+            paint(pos, COLLECTIBLE_COLOR);
+        }
+        for pos in level_data.objects.keys() {
+            paint(pos, OBJECT_COLOR);
+        }
+
+        pixels
+    }
+
+    // Draws in screen coordinates.
+    pub fn draw(&mut self, ctx: &Context, canvas: &mut Canvas, document: &Document) -> GameResult {
+        if !self.visible {
+            return Ok(());
+        }
+
+        let level_data = &document.level_data;
+        let screen_rect = document.screen_rect;
+        let layout = Self::layout(ctx, level_data);
+        let panel = layout.panel;
+
+        let background =
+            Mesh::new_rectangle(ctx, DrawMode::fill(), panel, Color::new(0.0, 0.0, 0.0, 0.8))?;
+        canvas.draw(&background, DrawParam::default());
+
+        let key = (document.id, document.revision);
+        if self.image.as_ref().is_none_or(|(cached, _)| *cached != key) {
+            let size = Self::pixel_size(&layout);
+            let image = Image::from_pixels(
+                ctx,
+                &Self::pixels(level_data, &layout),
+                ImageFormat::Rgba8UnormSrgb,
+                size.x as u32,
+                size.y as u32,
+            );
+            self.image = Some((key, image));
+        }
+
+        if let Some((_, image)) = &self.image {
+            let scale = layout.scale * layout.step as f32;
+            canvas.draw(
+                image,
+                DrawParam::default()
+                    .dest(Point2 {
+                        x: panel.x,
+                        y: panel.y,
+                    })
+                    .scale(Point2 { x: scale, y: scale }),
+            );
+        }
+
+        // The viewport may reach outside of the minimap, so it's clipped to the panel.
+        let size = MainState::CELL_SIZE as f32;
+        let left = panel.x + (screen_rect.x / size - layout.min.x as f32) * layout.scale;
+        let top = panel.y + (screen_rect.y / size - layout.min.y as f32) * layout.scale;
+        let right = left + screen_rect.w / size * layout.scale;
+        let bottom = top + screen_rect.h / size * layout.scale;
+
+        let (left, right) = (left.max(panel.left()), right.min(panel.right()));
+        let (top, bottom) = (top.max(panel.top()), bottom.min(panel.bottom()));
+
+        if left < right && top < bottom {
+            let viewport = Mesh::new_rectangle(
+                ctx,
+                DrawMode::stroke(1.0),
+                Rect::new(left, top, right - left, bottom - top),
+                Color::WHITE,
+            )?;
+            canvas.draw(&viewport, DrawParam::default());
+        }
+
+        Ok(())
+    }
+}
@@ -1,80 +1,351 @@
 use std::path::Path;
+use std::path::PathBuf;
+use std::sync::mpsc;
+use std::sync::mpsc::Receiver;
+use std::sync::mpsc::TryRecvError;
+use std::thread;
+use std::time::Duration;
+use std::time::Instant;
 
 use ggez::event;
 use ggez::event::MouseButton;
 use ggez::glam::IVec2;
 use ggez::graphics;
 use ggez::graphics::Color;
+use ggez::graphics::DrawMode;
 use ggez::graphics::DrawParam;
+use ggez::graphics::Mesh;
+use ggez::graphics::MeshBuilder;
 use ggez::graphics::Rect;
 use ggez::graphics::Sampler;
 use ggez::graphics::Text;
 use ggez::graphics::TextFragment;
+use ggez::input::keyboard::KeyInput;
+use ggez::input::keyboard::KeyMods;
 use ggez::mint::Point2;
+use ggez::winit::event::VirtualKeyCode;
 use ggez::Context;
-use ggez::GameResult;
 use ggez::GameError;
-use ggez::winit::event::VirtualKeyCode;
+use ggez::GameResult;
 
+use self::analysis::AnalysisOverlay;
+use self::autosave::Autosave;
+use self::bulk::BulkEdit;
+use self::document::{Document, Selection};
+use self::find::{Finder, Query};
+use self::game::Move;
+use self::generator::{Generated, GeneratorSettings};
+use self::grid::Grid;
+use self::history::History;
 use self::input_handler::InputHandler;
+use self::inspector::Inspector;
 use self::instances::wall::WallOrientation;
-use self::instances::LayerContent;
+use self::instances::Layer;
+use self::keybindings::{Action, KeyBindings};
+use self::layers::{layer_name, Layers};
 use self::level_data::LevelData;
+use self::links::Links;
+use self::metrics::StatsPanel;
+use self::minimap::Minimap;
+use self::palette::Palettes;
+use self::play::Play;
 use self::resources::Resources;
 
+mod analysis;
+mod autosave;
+mod bulk;
+mod config;
+mod document;
+pub mod export;
+mod find;
+mod game;
+pub mod generator;
+mod grid;
+mod history;
 mod input_handler;
+mod inspector;
 mod instances;
+mod keybindings;
+mod layers;
 mod level_data;
+mod links;
+pub mod metrics;
+mod minimap;
+mod palette;
+mod play;
+pub mod replay;
 mod resources;
 mod serialization;
+mod solver;
+mod sprite_manifest;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Confirmation {
+    Quit,
+    Load,
+    NewLevel,
+    CloseTab,
+    Restore,
+    Reload(usize),
+}
 
 pub struct MainState {
-    screen_rect: Rect,
     input_handler: InputHandler,
-    level_data: LevelData,
+    documents: Vec<Document>,
+    current: usize,
+    clipboard: Option<LevelData>,
+    inspector: Inspector,
+    grid: Grid,
+    links: Links,
+    layers: Layers,
+    minimap: Minimap,
+    play: Option<Play>,
+    stats: StatsPanel,
+    analysis: AnalysisOverlay,
+    finder: Finder,
+    // The seed the next generated level gets.
+    seed: u64,
+    // The level being generated on another thread, so the editor keeps responding.
+    generating: Option<Receiver<Result<Generated, String>>>,
     resources: Resources,
+    autosave: Autosave,
+    recovered: Vec<PathBuf>,
+    last_watch: Instant,
 
-    current_path: String,
     showing_error: bool,
     entering_text: bool,
+    // The path typed for saving or loading. It only becomes the document path
+    // once the level was written or read, so a declined load changes nothing.
+    entered_path: String,
+    // Text typed into the command line, while it is open.
+    command: Option<String>,
     entered_text_consumed: bool,
+    confirmation: Option<Confirmation>,
+    quitting: bool,
+    window_title: String,
 
     text_to_draw: String,
 }
 
 impl MainState {
     pub fn new(ctx: &Context) -> GameResult<MainState> {
+        let (key_bindings, mut problems) = KeyBindings::load(ctx);
+
         let mut ms = MainState {
-            screen_rect: Rect::new(0.0, 0.0, 128.0, 96.0),
-            input_handler: InputHandler::new(),
-            level_data: LevelData::new(),
+            input_handler: InputHandler::new(key_bindings),
+            documents: vec![Document::new()],
+            current: 0,
+            clipboard: None,
+            inspector: Inspector::new(),
+            grid: Grid::new(),
+            links: Links::new(),
+            layers: Layers::new(),
+            minimap: Minimap::new(),
+            play: None,
+            stats: StatsPanel::new(),
+            analysis: AnalysisOverlay::new(),
+            finder: Finder::new(),
+            seed: generator::time_seed(),
+            generating: None,
             resources: Resources::new(),
+            autosave: Autosave::new(ctx.fs.user_data_dir().join("recovery")),
+            recovered: Vec::new(),
+            last_watch: Instant::now(),
 
-            current_path: String::new(),
             showing_error: false,
             entering_text: false,
+            entered_path: String::new(),
+            command: None,
             entered_text_consumed: true,
+            confirmation: None,
+            quitting: false,
+            window_title: String::new(),
 
             text_to_draw: String::new(),
         };
 
-        ms.resources.initialize(ctx)?;
+        if let Err(error) = ms.resources.initialize(ctx) {
+            problems.push(error.to_string());
+        }
+
+        let (palettes, palette_problems) = Palettes::load(ctx);
+        ms.resources.palettes = palettes;
+        problems.extend(palette_problems);
+
+        if !problems.is_empty() {
+            ms.encountered_error(GameError::CustomError(problems.join("\n")));
+        }
+
+        ms.recovered = ms.autosave.recovered();
+        if !ms.recovered.is_empty() {
+            ms.confirmation = Some(Confirmation::Restore);
+        }
 
         Ok(ms)
     }
 
     const CELL_SIZE: i32 = 16;
+    const WATCH_INTERVAL: Duration = Duration::from_secs(1);
 
-    fn to_level_loader_coords(&self, ctx: &Context, coords: Point2<f32>) -> IVec2 {
+    fn document(&self) -> &Document {
+        &self.documents[self.current]
+    }
+
+    fn document_mut(&mut self) -> &mut Document {
+        &mut self.documents[self.current]
+    }
+
+    // `find <query>` highlights what matches, `find` alone stops, `select` selects
+    // the matching cells. Everything else is a bulk edit, working on
+    // the selection, or on the whole level without one, and undone as one step.
+    fn run_command(&mut self, command: &str) {
+        let (name, rest) = command
+            .trim()
+            .split_once(' ')
+            .unwrap_or((command.trim(), ""));
+
+        let result = match name {
+            "find" if rest.trim().is_empty() => {
+                self.finder.clear();
+                Ok(())
+            }
+            "find" => Query::parse(rest).map(|query| {
+                self.finder.find(query);
+                self.step_match(0);
+            }),
+            "select" => {
+                let document = &mut self.documents[self.current];
+                let matches = self.finder.matches(document);
+                match matches.is_empty() {
+                    true => Err(String::from("nothing found to select")),
+                    false => {
+                        document.selection =
+                            Some(Selection::Cells(matches.iter().copied().collect()));
+                        Ok(())
+                    }
+                }
+            }
+            _ => BulkEdit::parse(command).map(|edit| {
+                let region = self.document().selected_cells();
+                let changed = edit.apply(&self.document().level_data, region.as_ref());
+                let changed = self.layers.editable(changed);
+                self.document_mut().paste(&changed, IVec2::ZERO);
+            }),
+        };
+
+        if let Err(error) = result {
+            self.encountered_error(GameError::CustomError(error));
+        }
+    }
+
+    // Moves the camera to the next or previous match, or to the current one for 0.
+    fn step_match(&mut self, delta: i32) {
+        let document = &mut self.documents[self.current];
+        let Some(pos) = self.finder.step(document, delta) else {
+            return;
+        };
+
+        let size = Self::CELL_SIZE as f32;
+        let rect = &mut document.screen_rect;
+        rect.x = (pos.x as f32 + 0.5) * size - rect.w / 2.0;
+        rect.y = (pos.y as f32 + 0.5) * size - rect.h / 2.0;
+    }
+
+    // Starts generating a level, unless one is already being generated.
+    fn generate(&mut self, ctx: &Context) {
+        if self.generating.is_some() {
+            return;
+        }
+
+        let (settings, problems) = GeneratorSettings::load(ctx);
+        if !problems.is_empty() {
+            self.encountered_error(GameError::CustomError(problems.join("\n")));
+            return;
+        }
+
+        let seed = self.seed;
+        self.seed += 1;
+
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            // Nobody is waiting for the level anymore if the editor was closed meanwhile.
+            let _ = sender.send(generator::generate(&settings, seed));
+        });
+        self.generating = Some(receiver);
+    }
+
+    // Opens the generated level in a new tab, with its solution as the replay.
+    // Waits while playing, so the played level stays the current one.
+    fn receive_generated(&mut self) {
+        let Some(receiver) = &self.generating else {
+            return;
+        };
+        if self.play.is_some() {
+            return;
+        }
+
+        let result = match receiver.try_recv() {
+            Ok(result) => result,
+            Err(TryRecvError::Empty) => return,
+            Err(TryRecvError::Disconnected) => Err(String::from("the generator stopped")),
+        };
+        self.generating = None;
+
+        match result {
+            Ok(generated) => {
+                let mut document = Document::new();
+                document.level_data = generated.level_data;
+                document.title = Some(format!("generated_{}", generated.seed));
+                document.replay = Some(generated.solution);
+                document.saved = None;
+                document.mark_changed();
+
+                self.documents.push(document);
+                self.current = self.documents.len() - 1;
+                self.inspector = Inspector::new();
+            }
+            Err(error) => self.encountered_error(GameError::CustomError(error)),
+        }
+    }
+
+    fn switch_tab(&mut self, delta: i32) {
+        let count = self.documents.len() as i32;
+        self.current = (self.current as i32 + delta).rem_euclid(count) as usize;
+        self.inspector = Inspector::new();
+    }
+
+    fn close_tab(&mut self) {
+        let document = self.documents.remove(self.current);
+        self.autosave.remove(&document);
+
+        if self.documents.is_empty() {
+            self.documents.push(Document::new());
+        }
+
+        self.current = self.current.min(self.documents.len() - 1);
+        self.inspector = Inspector::new();
+    }
+
+    // Screen pixels to world units, taking the camera position and zoom into account.
+    fn to_world(&self, ctx: &Context, coords: Point2<f32>) -> Point2<f32> {
         let (size_x, size_y) = ctx.gfx.size();
-        let (size_x, size_y) = (self.screen_rect.w / size_x, self.screen_rect.h / size_y);
+        let screen_rect = self.document().screen_rect;
+
+        Point2 {
+            x: screen_rect.x + coords.x * screen_rect.w / size_x,
+            y: screen_rect.y + coords.y * screen_rect.h / size_y,
+        }
+    }
+
+    fn to_level_loader_coords(&self, ctx: &Context, coords: Point2<f32>) -> IVec2 {
+        let world = self.to_world(ctx, coords);
 
         let (x, y);
         unsafe {
-            x = (size_x * coords.x / (Self::CELL_SIZE as f32))
+            x = (world.x / (Self::CELL_SIZE as f32))
                 .floor()
                 .to_int_unchecked();
-            y = (size_y * coords.y / (Self::CELL_SIZE as f32))
+            y = (world.y / (Self::CELL_SIZE as f32))
                 .floor()
                 .to_int_unchecked();
         }
@@ -82,16 +353,490 @@ impl MainState {
         IVec2 { x, y }
     }
 
-    fn is_right(&self, ctx: &Context, coords: Point2<f32>) -> WallOrientation {
-        let (size_x, size_y) = ctx.gfx.size();
-        let (size_x, size_y) = (self.screen_rect.w / size_x, self.screen_rect.h / size_y);
+    // The cell edge nearest to the cursor. Top and left edges belong to the
+    // neighbors above and to the left, as their `down` and `right` walls.
+    fn wall_edge(&self, ctx: &Context, coords: Point2<f32>) -> (IVec2, WallOrientation) {
+        let world = self.to_world(ctx, coords);
+        let pos = self.to_level_loader_coords(ctx, coords);
+
+        let size = Self::CELL_SIZE as f32;
+        let x = world.x.rem_euclid(size);
+        let y = world.y.rem_euclid(size);
+
+        let edges = [
+            (size - x, pos, WallOrientation::Right),
+            (size - y, pos, WallOrientation::Down),
+            (x, pos - IVec2::X, WallOrientation::Right),
+            (y, pos - IVec2::Y, WallOrientation::Down),
+        ];
 
-        let x = size_x * coords.x % (Self::CELL_SIZE as f32);
-        let y = size_y * coords.y % (Self::CELL_SIZE as f32);
+        edges
+            .into_iter()
+            .min_by(|a, b| a.0.total_cmp(&b.0))
+            .map(|(_, pos, orientation)| (pos, orientation))
+            .unwrap_or((pos, WallOrientation::Right))
+    }
+
+    // Where edits on the current layer go: the hovered cell, or on the wall
+    // layer the cell owning the hovered edge.
+    fn target(&self, ctx: &Context, coords: Point2<f32>) -> (IVec2, WallOrientation) {
+        let (edge_pos, orientation) = self.wall_edge(ctx, coords);
 
-        match Self::CELL_SIZE as f32 - x < Self::CELL_SIZE as f32 - y {
-            true => WallOrientation::Right,
-            false => WallOrientation::Down,
+        match self.input_handler.layer() {
+            Layer::Wall(()) => (edge_pos, orientation),
+            _ => (self.to_level_loader_coords(ctx, coords), orientation),
+        }
+    }
+
+    // Highlights the edge the next wall edit goes to. Drawn in world coordinates.
+    fn draw_wall_edge(
+        &self,
+        ctx: &Context,
+        canvas: &mut graphics::Canvas,
+        pos: IVec2,
+        orientation: WallOrientation,
+    ) -> GameResult {
+        let size = Self::CELL_SIZE as f32;
+        let (x, y) = ((pos.x + 1) as f32 * size, (pos.y + 1) as f32 * size);
+
+        let points = match orientation {
+            WallOrientation::Right => [Point2 { x, y: y - size }, Point2 { x, y }],
+            WallOrientation::Down => [Point2 { x: x - size, y }, Point2 { x, y }],
+        };
+        let line = Mesh::new_line(ctx, &points, 2.0, Color::YELLOW)?;
+        canvas.draw(&line, DrawParam::default());
+
+        Ok(())
+    }
+
+    // Whether the mouse is over the level and not busy with the inspector, minimap or a
+    // locked layer, so pressing a button would edit it.
+    fn editing(&self, ctx: &Context) -> bool {
+        self.play.is_none()
+            && self.command.is_none()
+            && !self.inspector.open
+            && !self.minimap.hovered(ctx, &self.document().level_data)
+            && !self.layers.locked(self.input_handler.layer())
+    }
+
+    fn removing(&self, ctx: &Context) -> bool {
+        self.editing(ctx) && ctx.mouse.button_pressed(MouseButton::Right)
+    }
+
+    // What a left click would place: the clipboard at the cursor while Ctrl is held,
+    // otherwise the brush in the hovered cell or on the hovered wall edge.
+    fn preview(&self, ctx: &Context) -> LevelData {
+        let mut preview = LevelData::new();
+        if !self.editing(ctx) || ctx.keyboard.is_mod_active(KeyMods::SHIFT) {
+            return preview;
+        }
+
+        let mouse_pos = ctx.mouse.position();
+
+        if ctx.keyboard.is_mod_active(KeyMods::CTRL) {
+            if let Some(clipboard) = &self.clipboard {
+                let at = self.to_level_loader_coords(ctx, mouse_pos);
+                preview = self.layers.editable(clipboard.clone()).translated(at);
+            }
+        } else if !ctx.keyboard.is_mod_active(KeyMods::ALT) {
+            let (pos, orientation) = self.target(ctx, mouse_pos);
+            preview.insert(pos, self.input_handler.get_data(), orientation);
+        }
+
+        preview
+    }
+
+    // Red overlay over what a right click removes, if there is anything. Drawn in
+    // world coordinates.
+    fn draw_removal(
+        &self,
+        ctx: &Context,
+        canvas: &mut graphics::Canvas,
+        pos: IVec2,
+        orientation: WallOrientation,
+    ) -> GameResult {
+        let layer = self.input_handler.layer();
+        if self
+            .document()
+            .level_data
+            .get(pos, layer, orientation)
+            .is_none()
+        {
+            return Ok(());
+        }
+
+        let size = Self::CELL_SIZE as f32;
+        let (x, y) = (pos.x as f32 * size, pos.y as f32 * size);
+        let rect = match (layer, orientation) {
+            (Layer::Wall(()), WallOrientation::Right) => Rect::new(x + size - 2.0, y, 4.0, size),
+            (Layer::Wall(()), WallOrientation::Down) => Rect::new(x, y + size - 2.0, size, 4.0),
+            _ => Rect::new(x, y, size, size),
+        };
+
+        let overlay =
+            Mesh::new_rectangle(ctx, DrawMode::fill(), rect, Color::new(1.0, 0.0, 0.0, 0.5))?;
+        canvas.draw(&overlay, DrawParam::default());
+
+        Ok(())
+    }
+
+    fn pick(&mut self, ctx: &Context) {
+        let mouse_pos = ctx.mouse.position();
+
+        let (pos, orientation) = self.target(ctx, mouse_pos);
+
+        let picked = self
+            .document()
+            .level_data
+            .get(pos, self.input_handler.layer(), orientation);
+
+        if let Some(data) = picked {
+            self.input_handler.pick(data);
+        }
+    }
+
+    fn handle_action(&mut self, ctx: &mut Context, action: Action) {
+        match action {
+            Action::Pick => self.pick(ctx),
+
+            Action::Undo => self.document_mut().undo(),
+            Action::Redo => self.document_mut().redo(),
+
+            Action::Inspect => self.inspector.toggle(),
+            Action::InspectorPrevField => {
+                let document = &self.documents[self.current];
+                self.inspector.select(&document.level_data, -1)
+            }
+            Action::InspectorNextField => {
+                let document = &self.documents[self.current];
+                self.inspector.select(&document.level_data, 1)
+            }
+            Action::InspectorDecrease | Action::InspectorIncrease => {
+                let document = &mut self.documents[self.current];
+                let locked = self
+                    .inspector
+                    .layer(&document.level_data)
+                    .is_some_and(|layer| self.layers.locked(layer));
+
+                if !locked {
+                    let delta = match action {
+                        Action::InspectorDecrease => -1,
+                        _ => 1,
+                    };
+                    self.inspector.adjust(document, delta);
+                }
+            }
+
+            Action::NewTab => {
+                self.documents.push(Document::new());
+                self.current = self.documents.len() - 1;
+                self.inspector = Inspector::new();
+            }
+            Action::CloseTab => self.confirm(ctx, Confirmation::CloseTab),
+            Action::NewLevel => self.confirm(ctx, Confirmation::NewLevel),
+            Action::NextTab => self.switch_tab(1),
+            Action::PrevTab => self.switch_tab(-1),
+
+            Action::Copy => {
+                if let Some(clipboard) = self.document().copy() {
+                    self.clipboard = Some(clipboard);
+                }
+            }
+            Action::Cut => {
+                if let Some(clipboard) = self.document().copy() {
+                    let erased = self.layers.editable(clipboard.clone());
+                    self.document_mut().erase(&erased);
+                    self.clipboard = Some(clipboard);
+                }
+            }
+            Action::Paste => {
+                let at = self.to_level_loader_coords(ctx, ctx.mouse.position());
+                if let Some(clipboard) = &self.clipboard {
+                    let pasted = self.layers.editable(clipboard.clone());
+                    self.documents[self.current].paste(&pasted, at);
+                }
+            }
+            Action::ClearSelection => self.document_mut().selection = None,
+
+            Action::ToggleGrid => self.grid.toggle(),
+            Action::ToggleLinks => self.links.toggle(),
+            Action::NextPalette => self.resources.palettes.next(),
+            Action::ToggleGlyphs => self.resources.palettes.toggle_glyphs(),
+
+            Action::ToggleLayerHidden => self.layers.toggle_hidden(self.input_handler.layer()),
+            Action::ToggleLayerLocked => self.layers.toggle_locked(self.input_handler.layer()),
+            Action::ToggleDimInactive => self.layers.dim_inactive = !self.layers.dim_inactive,
+            Action::DimOpacityDown => self.layers.adjust_dim_opacity(-1.0),
+            Action::DimOpacityUp => self.layers.adjust_dim_opacity(1.0),
+            Action::SoloLayer => self.layers.solo = !self.layers.solo,
+
+            Action::ToggleMinimap => self.minimap.toggle(),
+
+            Action::Play => self.toggle_play(),
+            Action::PlayReplay => self.play_replay(),
+            Action::ToggleStats => self.stats.toggle(),
+            Action::ToggleAnalysis => self.analysis.toggle(),
+            Action::Generate => self.generate(ctx),
+            Action::Command => self.command = Some(String::new()),
+            Action::Find => self.command = Some(String::from("find ")),
+            Action::NextMatch => self.step_match(1),
+            Action::PrevMatch => self.step_match(-1),
+
+            _ => (),
+        }
+    }
+
+    // Asks before throwing away unsaved changes, otherwise proceeds right away.
+    fn confirm(&mut self, ctx: &mut Context, confirmation: Confirmation) {
+        let needed = match confirmation {
+            Confirmation::Quit => self.documents.iter().any(|document| document.dirty()),
+            _ => self.document().dirty(),
+        };
+
+        match needed {
+            true => self.confirmation = Some(confirmation),
+            false => self.proceed(ctx, confirmation),
+        }
+    }
+
+    fn proceed(&mut self, ctx: &mut Context, confirmation: Confirmation) {
+        match confirmation {
+            Confirmation::Quit => {
+                for document in &self.documents {
+                    self.autosave.remove(document);
+                }
+
+                self.quitting = true;
+                ctx.request_quit();
+            }
+            Confirmation::Load => self.load(),
+            Confirmation::NewLevel => {
+                self.autosave.remove(self.document());
+                *self.document_mut() = Document::new();
+                self.inspector = Inspector::new();
+            }
+            Confirmation::CloseTab => self.close_tab(),
+            Confirmation::Restore => self.restore(),
+            Confirmation::Reload(index) => {
+                let path = self.documents[index].path.clone();
+                self.load_document(index, path);
+            }
+        }
+    }
+
+    fn cancel(&mut self, confirmation: Confirmation) {
+        if let Confirmation::Restore = confirmation {
+            Autosave::discard(&self.recovered);
+            self.recovered.clear();
+        }
+    }
+
+    fn restore(&mut self) {
+        let untouched = self.documents.len() == 1
+            && !self.document().dirty()
+            && self.document().path.is_empty();
+        if untouched {
+            self.documents.clear();
+        }
+
+        for path in std::mem::take(&mut self.recovered) {
+            match Autosave::restore(&path) {
+                Ok(document) => {
+                    self.documents.push(document);
+                    Autosave::discard(&[path]);
+                }
+                Err(error) => self.encountered_error(error),
+            }
+        }
+
+        if self.documents.is_empty() {
+            self.documents.push(Document::new());
+        }
+        self.current = self.documents.len() - 1;
+    }
+
+    fn confirmation_text(&self, confirmation: Confirmation) -> String {
+        match confirmation {
+            Confirmation::Restore => format!(
+                "Found {} level(s) left from a session that did not exit cleanly. Restore? (y/n)",
+                self.recovered.len()
+            ),
+            Confirmation::Reload(index) => format!(
+                "{} was changed on disk. Reload it and lose your edits? (y/n)",
+                self.documents[index].name()
+            ),
+            Confirmation::Quit => {
+                let unsaved = self.documents.iter().filter(|d| d.dirty()).count();
+                format!(
+                    "{} level(s) have unsaved changes. Quit anyway? (y/n)",
+                    unsaved
+                )
+            }
+            _ => format!(
+                "{} has unsaved changes. Discard them? (y/n)",
+                self.document().name()
+            ),
+        }
+    }
+
+    fn load(&mut self) {
+        self.load_document(self.current, self.entered_path.clone());
+    }
+
+    // Replaces the level with the file contents, the camera stays where it was.
+    fn load_document(&mut self, index: usize, path: String) {
+        let result = serialization::load(Path::new(&path));
+
+        match result {
+            Ok(data) => {
+                self.autosave.remove(&self.documents[index]);
+
+                let document = &mut self.documents[index];
+                document.path = path;
+                document.level_data = data;
+                document.revision += 1;
+                document.history = History::new();
+                document.mark_saved();
+                document.selection = None;
+                document.modified = document.file_modified();
+                document.load_replay();
+            }
+            Err(error) => self.encountered_error(error),
+        }
+    }
+
+    // Picks up levels that were changed by other programs. Not while a path is
+    // typed, a save or load is about to touch the files anyway.
+    fn watch_files(&mut self) {
+        if self.confirmation.is_some()
+            || self.entering_text
+            || self.last_watch.elapsed() < Self::WATCH_INTERVAL
+        {
+            return;
+        }
+        self.last_watch = Instant::now();
+
+        for index in 0..self.documents.len() {
+            let document = &mut self.documents[index];
+            if !document.changed_on_disk() {
+                continue;
+            }
+            document.modified = document.file_modified();
+
+            match document.dirty() {
+                true => {
+                    self.current = index;
+                    self.inspector = Inspector::new();
+                    self.confirmation = Some(Confirmation::Reload(index));
+                    return;
+                }
+                false => {
+                    let path = self.documents[index].path.clone();
+                    self.load_document(index, path);
+                }
+            }
+        }
+    }
+
+    fn toggle_play(&mut self) {
+        if self.play.take().is_some() {
+            return;
+        }
+
+        match Play::new(&self.document().level_data) {
+            Ok(play) => self.play = Some(play),
+            Err(error) => self.encountered_error(GameError::CustomError(error)),
+        }
+    }
+
+    // Plays the replay saved next to the level from the start.
+    fn play_replay(&mut self) {
+        let Some(moves) = self.document().replay.clone() else {
+            self.encountered_error(GameError::CustomError(String::from(
+                "The level has no replay",
+            )));
+            return;
+        };
+
+        let result = Play::new(&self.document().level_data).and_then(|mut play| {
+            let loaded = play.load(&moves);
+            self.play = Some(play);
+            loaded
+        });
+
+        if let Err(error) = result {
+            self.encountered_error(GameError::CustomError(error));
+        }
+    }
+
+    fn save_replay(&mut self) {
+        let Some(play) = &self.play else {
+            return;
+        };
+        if self.document().path.is_empty() {
+            self.encountered_error(GameError::CustomError(String::from(
+                "Save the level before its replay",
+            )));
+            return;
+        }
+
+        let moves = play.moves().to_vec();
+        let path = replay::path(Path::new(&self.document().path));
+
+        match replay::save(&path, &moves) {
+            Ok(()) => {
+                let document = self.document_mut();
+                document.replay = Some(moves);
+                document.check_replay();
+            }
+            Err(error) => self.encountered_error(error),
+        }
+    }
+
+    // While playing, keys move the player and step through the moves, only the
+    // bindings that leave or restart play mode work as usual.
+    fn handle_play_input(&mut self, ctx: &mut Context, input: KeyInput) {
+        let Some(play) = &mut self.play else {
+            return;
+        };
+
+        let m = match input.keycode {
+            Some(VirtualKeyCode::Up) => Some(Move::Up),
+            Some(VirtualKeyCode::Down) => Some(Move::Down),
+            Some(VirtualKeyCode::Left) => Some(Move::Left),
+            Some(VirtualKeyCode::Right) => Some(Move::Right),
+            _ => None,
+        };
+        if let Some(m) = m {
+            play.make_move(m);
+            return;
+        }
+
+        match input.keycode {
+            Some(VirtualKeyCode::Space) => play.toggle_playing(),
+            Some(VirtualKeyCode::Comma) | Some(VirtualKeyCode::Back) => play.step_back(),
+            Some(VirtualKeyCode::Period) => play.step_forward(),
+            Some(VirtualKeyCode::Escape) => self.play = None,
+            Some(VirtualKeyCode::S) if input.mods == KeyMods::CTRL => self.save_replay(),
+
+            _ => {
+                if let Some(action @ (Action::Play | Action::PlayReplay)) =
+                    self.input_handler.bound_action(&input)
+                {
+                    self.handle_action(ctx, action);
+                }
+            }
+        }
+    }
+
+    fn update_window_title(&mut self, ctx: &Context) {
+        let document = self.document();
+        let dirty = if document.dirty() { "*" } else { "" };
+        let title = format!("{}{} - level editor", document.name(), dirty);
+
+        if title != self.window_title {
+            ctx.gfx.set_window_title(&title);
+            self.window_title = title;
         }
     }
 
@@ -103,43 +848,117 @@ impl MainState {
 
 impl event::EventHandler<ggez::GameError> for MainState {
     fn update(&mut self, ctx: &mut Context) -> GameResult {
-        // Adding
-        if ctx.mouse.button_pressed(MouseButton::Left) {
-            let mouse_pos = ctx.mouse.position();
-            let ll_coords = self.to_level_loader_coords(ctx, mouse_pos);
+        let document = &mut self.documents[self.current];
+        let on_minimap = self
+            .minimap
+            .update(ctx, &document.level_data, &mut document.screen_rect);
 
-            self.level_data.insert(
-                ll_coords,
-                self.input_handler.get_data(),
-                self.is_right(ctx, mouse_pos),
-            )
+        if let Some(play) = &mut self.play {
+            play.update();
+        }
+
+        // Moving the camera is done by the minimap itself, and while playing or
+        // typing a command the level can't be edited
+        if !on_minimap && self.play.is_none() && self.command.is_none() {
+            // Inspecting
+            if self.inspector.open {
+                if ctx.mouse.button_pressed(MouseButton::Left) {
+                    let mouse_pos = ctx.mouse.position();
+                    self.inspector
+                        .inspect(self.to_level_loader_coords(ctx, mouse_pos));
+                }
+            }
+            // Selecting
+            else if ctx.mouse.button_pressed(MouseButton::Left)
+                && ctx.keyboard.is_mod_active(KeyMods::SHIFT)
+            {
+                let mouse_pos = ctx.mouse.position();
+                let ll_coords = self.to_level_loader_coords(ctx, mouse_pos);
+
+                let document = self.document_mut();
+                let start = match (
+                    ctx.mouse.button_just_pressed(MouseButton::Left),
+                    &document.selection,
+                ) {
+                    (false, Some(Selection::Rect(start, _))) => *start,
+                    _ => ll_coords,
+                };
+                document.selection = Some(Selection::Rect(start, ll_coords));
+            }
+            // Picking
+            else if ctx.mouse.button_pressed(MouseButton::Left)
+                && ctx.keyboard.is_mod_active(KeyMods::ALT)
+            {
+                self.pick(ctx);
+            }
+            // Adding
+            else if ctx.mouse.button_pressed(MouseButton::Left)
+                && !self.layers.locked(self.input_handler.layer())
+            {
+                let mouse_pos = ctx.mouse.position();
+                let (ll_coords, orientation) = self.target(ctx, mouse_pos);
+                let data = self.input_handler.get_data();
+
+                let layer = self.input_handler.layer();
+
+                self.document_mut().edit(ll_coords, layer, |level_data| {
+                    level_data.insert(ll_coords, data, orientation)
+                });
+            }
         }
 
         // Deliting
-        if ctx.mouse.button_pressed(MouseButton::Right) {
+        if self.removing(ctx) {
             let mouse_pos = ctx.mouse.position();
+            let (ll_coords, orientation) = self.target(ctx, mouse_pos);
+            let layer = self.input_handler.layer();
 
-            self.level_data.remove(
-                self.to_level_loader_coords(ctx, mouse_pos),
-                self.input_handler.layer(),
-                self.is_right(ctx, mouse_pos),
-            )
+            self.document_mut().edit(ll_coords, layer, |level_data| {
+                level_data.remove(ll_coords, layer, orientation)
+            });
         }
 
+        if !ctx.mouse.button_pressed(MouseButton::Left)
+            && !ctx.mouse.button_pressed(MouseButton::Right)
+        {
+            self.document_mut().commit();
+        }
 
         if self.input_handler.request_save {
             if self.entered_text_consumed {
                 self.entering_text = true;
-                self.text_to_draw = self.current_path.clone();
+                self.entered_path = self.document().path.clone();
+                self.text_to_draw = self.entered_path.clone();
             } else {
-                let result = serialization::save(
-                    &self.level_data,
-                    Path::new(&self.current_path),
-                );
+                let path = self.entered_path.clone();
+                let result = serialization::save(&self.document().level_data, Path::new(&path));
                 self.entered_text_consumed = true;
 
-                if let Err(error) = result {
-                    self.encountered_error(error);
+                match result {
+                    Ok(()) => {
+                        self.autosave.remove(self.document());
+
+                        let document = self.document_mut();
+                        document.path = path;
+                        document.mark_saved();
+                        document.modified = document.file_modified();
+
+                        // A replay that is only in memory, like the solution of a generated
+                        // level, is saved along with the level.
+                        let saved = match &document.replay {
+                            Some(moves) => {
+                                replay::save(&replay::path(Path::new(&document.path)), moves)
+                            }
+                            None => {
+                                document.load_replay();
+                                Ok(())
+                            }
+                        };
+                        if let Err(error) = saved {
+                            self.encountered_error(error);
+                        }
+                    }
+                    Err(error) => self.encountered_error(error),
                 }
 
                 self.input_handler.request_save = false;
@@ -148,20 +967,37 @@ impl event::EventHandler<ggez::GameError> for MainState {
         if self.input_handler.request_load {
             if self.entered_text_consumed {
                 self.entering_text = true;
-                self.text_to_draw = self.current_path.clone();
+                self.entered_path = self.document().path.clone();
+                self.text_to_draw = self.entered_path.clone();
             } else {
-                let result = serialization::load(Path::new(&self.current_path));
                 self.entered_text_consumed = true;
-
-                match result {
-                    Ok(data) => self.level_data = data,
-                    Err(error) => self.encountered_error(error),
-                }
+                self.confirm(ctx, Confirmation::Load);
 
                 self.input_handler.request_load = false;
             }
         }
 
+        if self.autosave.is_due() {
+            for i in 0..self.documents.len() {
+                if let Err(error) = self.autosave.save(&mut self.documents[i]) {
+                    self.encountered_error(error);
+                }
+            }
+        }
+
+        let painting = ctx.mouse.button_pressed(MouseButton::Left)
+            || ctx.mouse.button_pressed(MouseButton::Right);
+        self.stats.update(&self.documents[self.current], painting);
+        self.analysis
+            .update(&self.documents[self.current], painting);
+
+        self.receive_generated();
+        self.watch_files();
+        if let Err(error) = self.resources.watch(ctx) {
+            self.encountered_error(error);
+        }
+        self.update_window_title(ctx);
+
         Ok(())
     }
 
@@ -172,14 +1008,16 @@ impl event::EventHandler<ggez::GameError> for MainState {
         y: f32,
     ) -> Result<(), ggez::GameError> {
         let scale_factor = 1.1_f32.powf(-y);
-        self.screen_rect.scale(scale_factor, scale_factor);
+        self.document_mut()
+            .screen_rect
+            .scale(scale_factor, scale_factor);
 
         Ok(())
     }
 
     fn key_down_event(
         &mut self,
-        _ctx: &mut Context,
+        ctx: &mut Context,
         input: ggez::input::keyboard::KeyInput,
         _repeated: bool,
     ) -> Result<(), ggez::GameError> {
@@ -188,11 +1026,28 @@ impl event::EventHandler<ggez::GameError> for MainState {
             self.text_to_draw.clear();
         }
 
+        if let Some(confirmation) = self.confirmation {
+            match input.keycode {
+                Some(VirtualKeyCode::Y) | Some(VirtualKeyCode::Return) => {
+                    self.confirmation = None;
+                    self.proceed(ctx, confirmation);
+                }
+                Some(VirtualKeyCode::N) | Some(VirtualKeyCode::Escape) => {
+                    self.confirmation = None;
+                    self.cancel(confirmation);
+                }
+
+                _ => (),
+            }
+
+            return Ok(());
+        }
+
         if self.entering_text {
             match input.keycode {
                 Some(VirtualKeyCode::Back) => {
-                    self.current_path.pop();
-                    self.text_to_draw = self.current_path.clone();
+                    self.entered_path.pop();
+                    self.text_to_draw = self.entered_path.clone();
                 }
                 Some(VirtualKeyCode::Return) => {
                     self.entering_text = false;
@@ -202,49 +1057,280 @@ impl event::EventHandler<ggez::GameError> for MainState {
 
                 _ => (),
             }
-        } else {
-            self.input_handler.handle_input(input);
+        } else if let Some(command) = &mut self.command {
+            match input.keycode {
+                Some(VirtualKeyCode::Back) => {
+                    command.pop();
+                }
+                Some(VirtualKeyCode::Return) | Some(VirtualKeyCode::NumpadEnter) => {
+                    if let Some(command) = self.command.take() {
+                        self.run_command(&command);
+                    }
+                }
+                Some(VirtualKeyCode::Escape) => self.command = None,
+
+                _ => (),
+            }
+        } else if self.play.is_some() {
+            self.handle_play_input(ctx, input);
+        } else if let Some(action) = self.input_handler.handle_input(input) {
+            self.handle_action(ctx, action);
         }
 
         Ok(())
     }
 
-    fn text_input_event(&mut self, _ctx: &mut Context, character: char) -> Result<(), ggez::GameError> {
+    fn text_input_event(
+        &mut self,
+        _ctx: &mut Context,
+        character: char,
+    ) -> Result<(), ggez::GameError> {
         if self.entering_text {
             if !character.is_control() {
-                self.current_path.push(character);
-                self.text_to_draw = self.current_path.clone();
+                self.entered_path.push(character);
+                self.text_to_draw = self.entered_path.clone();
+            }
+        } else if let Some(command) = &mut self.command {
+            if !character.is_control() {
+                command.push(character);
             }
         }
 
         Ok(())
     }
 
+    fn quit_event(&mut self, ctx: &mut Context) -> Result<bool, ggez::GameError> {
+        if self.quitting {
+            return Ok(false);
+        }
+
+        self.confirm(ctx, Confirmation::Quit);
+
+        Ok(!self.quitting)
+    }
+
     fn draw(&mut self, ctx: &mut Context) -> GameResult {
         let mut canvas = graphics::Canvas::from_frame(ctx, Some(Color::BLACK));
-        canvas.set_screen_coordinates(self.screen_rect);
+        canvas.set_screen_coordinates(self.document().screen_rect);
         canvas.set_sampler(Sampler::nearest_clamp());
 
         let mouse_pos = ctx.mouse.position();
 
-        let cntntn =
-            LayerContent::new(self.input_handler.get_data(), self.is_right(ctx, mouse_pos));
+        let (target, orientation) = self.target(ctx, mouse_pos);
+        let removing = self.removing(ctx);
+        let preview = match removing {
+            true => LevelData::new(),
+            false => self.preview(ctx),
+        };
+
+        let played = self.play.as_ref().map(Play::level_data);
+        let level_data = played.as_ref().unwrap_or(&self.document().level_data);
 
-        let result = self.level_data.draw_with(
+        let result = level_data.draw_with(ctx, &preview, &mut canvas, &self.resources, |layer| {
+            self.layers.opacity(layer, self.input_handler.layer())
+        });
+
+        self.grid
+            .draw(ctx, &mut canvas, self.document().screen_rect, level_data)?;
+        self.links.draw(
             ctx,
-            cntntn,
-            &self.to_level_loader_coords(ctx, mouse_pos),
             &mut canvas,
-            &self.resources,
-        );
+            self.document().screen_rect,
+            level_data,
+            &self.resources.palettes,
+            self.to_level_loader_coords(ctx, mouse_pos),
+        )?;
 
         if let Err(error) = result {
             self.encountered_error(error);
         }
 
+        if self.play.is_none() {
+            self.analysis.draw(ctx, &mut canvas)?;
+            self.finder
+                .draw(ctx, &mut canvas, &self.documents[self.current])?;
+        }
+
+        if removing {
+            self.draw_removal(ctx, &mut canvas, target, orientation)?;
+        } else if self.editing(ctx) && self.input_handler.layer() == Layer::Wall(()) {
+            self.draw_wall_edge(ctx, &mut canvas, target, orientation)?;
+        }
+
+        if self.inspector.open {
+            self.inspector.draw_cell(ctx, &mut canvas)?;
+        }
+
+        let size = Self::CELL_SIZE as f32;
+        let cell_rect = |min: IVec2, max: IVec2| {
+            Rect::new(
+                min.x as f32 * size,
+                min.y as f32 * size,
+                (max.x - min.x + 1) as f32 * size,
+                (max.y - min.y + 1) as f32 * size,
+            )
+        };
+        match &self.document().selection {
+            Some(Selection::Cells(cells)) => {
+                let mut builder = MeshBuilder::new();
+                for pos in cells {
+                    builder.rectangle(DrawMode::stroke(1.0), cell_rect(*pos, *pos), Color::CYAN)?;
+                }
+
+                let outlines = Mesh::from_data(ctx, builder.build());
+                canvas.draw(&outlines, DrawParam::default());
+            }
+            Some(Selection::Rect(..)) => {
+                if let Some((min, max)) = self.document().selection_rect() {
+                    let outline = Mesh::new_rectangle(
+                        ctx,
+                        DrawMode::stroke(1.0),
+                        cell_rect(min, max),
+                        Color::CYAN,
+                    )?;
+                    canvas.draw(&outline, DrawParam::default());
+                }
+            }
+            None => (),
+        }
+
+        let (width, height) = ctx.gfx.size();
+        canvas.set_screen_coordinates(Rect::new(0.0, 0.0, width, height));
+
+        if self.inspector.open {
+            self.inspector
+                .draw(ctx, &mut canvas, &self.document().level_data)?;
+        }
+
+        self.stats.draw(ctx, &mut canvas)?;
+
+        self.minimap
+            .draw(ctx, &mut canvas, &self.documents[self.current])?;
+
+        let tabs = self
+            .documents
+            .iter()
+            .enumerate()
+            .map(|(i, document)| {
+                let dirty = if document.dirty() { "*" } else { "" };
+                match i == self.current {
+                    true => format!("[{}{}]", document.name(), dirty),
+                    false => format!(" {}{} ", document.name(), dirty),
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(" ");
+        canvas.draw(&Text::new(tabs), DrawParam::default());
+
+        if let Some(command) = &self.command {
+            let text = Text::new(format!(
+                "command: {}_   enter - run, escape - cancel",
+                command
+            ));
+            canvas.draw(
+                &text,
+                DrawParam::default().dest(Point2 {
+                    x: 0.0,
+                    y: height - 40.0,
+                }),
+            );
+        }
+
+        if let Some(entry) = self.input_handler.durability_input() {
+            let text = Text::new(format!(
+                "durability: {}_   enter - set, i - indestructible",
+                entry
+            ));
+            canvas.draw(
+                &text,
+                DrawParam::default().dest(Point2 {
+                    x: 0.0,
+                    y: height - 40.0,
+                }),
+            );
+        }
+
+        let hovered = self.to_level_loader_coords(ctx, mouse_pos);
+        let (edge_pos, edge_orientation) = self.wall_edge(ctx, mouse_pos);
+        let layer = self.input_handler.layer();
+        let mut layer_state = String::from(layer_name(layer));
+        let dim = format!(" dim others {:.0}%", self.layers.dim_opacity * 100.0);
+        for (active, name) in [
+            (self.layers.hidden(layer), " hidden"),
+            (self.layers.locked(layer), " locked"),
+            (self.layers.solo, " solo"),
+            (self.layers.dim_inactive, dim.as_str()),
+        ] {
+            if active {
+                layer_state.push_str(name);
+            }
+        }
+
+        let mut status = Text::new(match &self.play {
+            Some(play) => format!("{}   ", play.status()),
+            None => String::new(),
+        });
+        status.add(format!(
+            "cell {},{}   wall edge: {},{} {}   layer: {}   palette: {}",
+            hovered.x,
+            hovered.y,
+            edge_pos.x,
+            edge_pos.y,
+            edge_orientation.name(),
+            layer_state,
+            self.resources.palettes.current().name
+        ));
+        match &self.document().replay_check {
+            Some(Ok(moves)) => {
+                status.add(format!("   replay: solved in {} moves", moves));
+            }
+            Some(Err(error)) => {
+                status.add(TextFragment::new(format!("   replay: {}", error)).color(Color::RED));
+            }
+            None => (),
+        }
+        if let Some(find) = self.finder.status(&self.documents[self.current]) {
+            status.add(format!("   {}", find));
+        }
+        if let Some(analysis) = self.analysis.analysis() {
+            status.add(format!("   {}", analysis.summary()));
+        }
+        if self.generating.is_some() {
+            status.add("   generating...");
+        }
+        canvas.draw(
+            &status,
+            DrawParam::default().dest(Point2 {
+                x: 0.0,
+                y: height - 20.0,
+            }),
+        );
+
+        if let Some(confirmation) = self.confirmation {
+            let text = Text::new(self.confirmation_text(confirmation));
+            let bounds = text.measure(ctx)?;
+            let panel = Rect::new(
+                (width - bounds.x) / 2.0 - 8.0,
+                (height - bounds.y) / 2.0 - 8.0,
+                bounds.x + 16.0,
+                bounds.y + 16.0,
+            );
+            let background =
+                Mesh::new_rectangle(ctx, DrawMode::fill(), panel, Color::new(0.0, 0.0, 0.0, 0.8))?;
+
+            canvas.draw(&background, DrawParam::default());
+            canvas.draw(
+                &text,
+                DrawParam::default().dest(Point2 {
+                    x: panel.x + 8.0,
+                    y: panel.y + 8.0,
+                }),
+            );
+        }
+
         let fragment = TextFragment::from(self.text_to_draw.clone());
         let text = Text::new(fragment);
-        canvas.draw(&text, DrawParam::default());
+        canvas.draw(&text, DrawParam::default().dest(Point2 { x: 0.0, y: 20.0 }));
 
         if let Err(error) = canvas.finish(ctx) {
             self.encountered_error(error);
@@ -0,0 +1,159 @@
+use ggez::{graphics::Color, Context};
+
+use super::{
+    config,
+    instances::{ActivatingColor, COLORS, COLOR_NAMES},
+};
+
+const CONFIG_PATH: &str = "/palette.toml";
+
+// Okabe-Ito colors, which stay distinct for the common kinds of color blindness.
+const COLORBLIND: [u32; 7] = [
+    0xffffff, 0xd55e00, 0x0072b2, 0x009e73, 0xf0e442, 0x56b4e9, 0xcc79a7,
+];
+
+fn index(color: ActivatingColor) -> usize {
+    COLORS.iter().position(|c| *c == color).unwrap_or(0)
+}
+
+#[derive(Debug)]
+pub struct Palette {
+    pub name: String,
+    colors: [Color; 7],
+}
+
+impl Palette {
+    fn default_colors() -> [Color; 7] {
+        COLORS.map(|color| color.into())
+    }
+}
+
+#[derive(Debug)]
+pub struct Palettes {
+    palettes: Vec<Palette>,
+    current: usize,
+    pub glyphs: bool,
+}
+
+impl Palettes {
+    pub fn new() -> Palettes {
+        Palettes {
+            palettes: vec![
+                Palette {
+                    name: String::from("default"),
+                    colors: Palette::default_colors(),
+                },
+                Palette {
+                    name: String::from("colorblind"),
+                    colors: COLORBLIND.map(Color::from_rgb_u32),
+                },
+            ],
+            current: 0,
+            glyphs: false,
+        }
+    }
+
+    // Reads `palette.toml` from the resources or user config directory.
+    pub fn load(ctx: &Context) -> (Palettes, Vec<String>) {
+        let mut palettes = Palettes::new();
+        let mut problems = Vec::new();
+
+        let Some(config) = config::load(ctx, CONFIG_PATH, &mut problems) else {
+            return (palettes, problems);
+        };
+
+        let tables = config
+            .get("palettes")
+            .and_then(toml::Value::as_table)
+            .cloned()
+            .unwrap_or_default();
+
+        for (name, table) in tables {
+            let mut colors = Palette::default_colors();
+
+            for (color, value) in table.as_table().cloned().unwrap_or_default() {
+                let Some((activating_color, _)) = COLOR_NAMES.iter().find(|(_, n)| *n == color)
+                else {
+                    problems.push(format!(
+                        "{}: {}: unknown color `{}`",
+                        CONFIG_PATH, name, color
+                    ));
+                    continue;
+                };
+
+                match value.as_str().and_then(parse_hex) {
+                    Some(value) => colors[index(*activating_color)] = value,
+                    None => problems.push(format!(
+                        "{}: {}: `{}` must look like \"#rrggbb\"",
+                        CONFIG_PATH, name, color
+                    )),
+                }
+            }
+
+            palettes.palettes.retain(|palette| palette.name != name);
+            palettes.palettes.push(Palette { name, colors });
+        }
+
+        if let Some(glyphs) = config.get("glyphs") {
+            match glyphs.as_bool() {
+                Some(glyphs) => palettes.glyphs = glyphs,
+                None => problems.push(format!("{}: `glyphs` must be true or false", CONFIG_PATH)),
+            }
+        }
+
+        if let Some(name) = config.get("palette") {
+            let position = name
+                .as_str()
+                .and_then(|name| palettes.palettes.iter().position(|p| p.name == name));
+
+            match position {
+                Some(position) => palettes.current = position,
+                None => problems.push(format!("{}: unknown palette {}", CONFIG_PATH, name)),
+            }
+        }
+
+        (palettes, problems)
+    }
+
+    pub fn current(&self) -> &Palette {
+        &self.palettes[self.current]
+    }
+
+    pub fn next(&mut self) {
+        self.current = (self.current + 1) % self.palettes.len();
+    }
+
+    pub fn toggle_glyphs(&mut self) {
+        self.glyphs = !self.glyphs;
+    }
+
+    pub fn color(&self, color: ActivatingColor) -> Color {
+        self.current().colors[index(color)]
+    }
+
+    // Letter drawn over tinted sprites, so colors can be told apart without hue.
+    pub fn glyph(&self, color: ActivatingColor) -> Option<&'static str> {
+        if !self.glyphs {
+            return None;
+        }
+
+        match color {
+            ActivatingColor::None => None,
+            ActivatingColor::Red => Some("R"),
+            ActivatingColor::Blue => Some("B"),
+            ActivatingColor::Green => Some("G"),
+            ActivatingColor::Yellow => Some("Y"),
+            ActivatingColor::Cyan => Some("C"),
+            ActivatingColor::Pink => Some("P"),
+        }
+    }
+}
+
+fn parse_hex(value: &str) -> Option<Color> {
+    let hex = value.strip_prefix('#')?;
+    if hex.len() != 6 {
+        return None;
+    }
+
+    Some(Color::from_rgb_u32(u32::from_str_radix(hex, 16).ok()?))
+}
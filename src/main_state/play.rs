@@ -0,0 +1,119 @@
+use std::time::{Duration, Instant};
+
+use super::{
+    game::{Game, Move, State},
+    level_data::LevelData,
+};
+
+// Playing the current level inside the editor, by hand or by watching a replay.
+// Every state is kept, so stepping back and forth through the moves is instant.
+pub struct Play {
+    game: Game,
+    states: Vec<State>,
+    moves: Vec<Move>,
+    step: usize,
+    playing: bool,
+    last_step: Instant,
+}
+
+impl Play {
+    const STEP_INTERVAL: Duration = Duration::from_millis(250);
+
+    pub fn new(level_data: &LevelData) -> Result<Play, String> {
+        let game = Game::new(level_data)?;
+
+        Ok(Play {
+            states: vec![game.start().clone()],
+            game,
+            moves: Vec::new(),
+            step: 0,
+            playing: false,
+            last_step: Instant::now(),
+        })
+    }
+
+    // Takes recorded moves and starts playing them from the beginning. If one of
+    // them is blocked, the moves before it are kept and the error is returned.
+    pub fn load(&mut self, moves: &[Move]) -> Result<(), String> {
+        self.states.truncate(1);
+        self.moves.clear();
+        self.step = 0;
+        self.playing = true;
+        self.last_step = Instant::now();
+
+        for (i, m) in moves.iter().enumerate() {
+            let Some(next) = self.game.step(&self.states[i], *m) else {
+                return Err(format!("move {} ({}) is blocked", i + 1, m.letter()));
+            };
+            self.states.push(next);
+            self.moves.push(*m);
+        }
+
+        Ok(())
+    }
+
+    pub fn moves(&self) -> &[Move] {
+        &self.moves
+    }
+
+    // Moving while an earlier step is shown drops the moves after it.
+    pub fn make_move(&mut self, m: Move) {
+        self.playing = false;
+
+        if let Some(next) = self.game.step(&self.states[self.step], m) {
+            self.states.truncate(self.step + 1);
+            self.moves.truncate(self.step);
+            self.states.push(next);
+            self.moves.push(m);
+            self.step += 1;
+        }
+    }
+
+    pub fn step_back(&mut self) {
+        self.playing = false;
+        self.step = self.step.saturating_sub(1);
+    }
+
+    pub fn step_forward(&mut self) {
+        self.playing = false;
+        self.step = (self.step + 1).min(self.moves.len());
+    }
+
+    // Playing from the last step starts over.
+    pub fn toggle_playing(&mut self) {
+        if !self.playing && self.step == self.moves.len() {
+            self.step = 0;
+        }
+        self.playing = !self.playing;
+        self.last_step = Instant::now();
+    }
+
+    pub fn update(&mut self) {
+        if !self.playing || self.last_step.elapsed() < Self::STEP_INTERVAL {
+            return;
+        }
+
+        match self.step < self.moves.len() {
+            true => self.step += 1,
+            false => self.playing = false,
+        }
+        self.last_step = Instant::now();
+    }
+
+    // The level in the shown state.
+    pub fn level_data(&self) -> LevelData {
+        self.game.level_data(&self.states[self.step])
+    }
+
+    pub fn status(&self) -> String {
+        let mut status = format!("play: move {}/{}", self.step, self.moves.len());
+        if self.playing {
+            status.push_str(" playing");
+        }
+        if self.game.solved(&self.states[self.step]) {
+            status.push_str(" solved");
+        }
+
+        status
+    }
+}
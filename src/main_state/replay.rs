@@ -0,0 +1,168 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use ggez::{GameError, GameResult};
+
+use super::{
+    game::{Game, Move},
+    level_data::LevelData,
+    serialization,
+};
+
+// Replays are the moves of a solution written as letters (U, D, L, R) in a file
+// next to the level, `level.replay` for `level`. Whitespace is ignored and `#`
+// starts a comment.
+pub const EXTENSION: &str = "replay";
+
+const MOVES_PER_LINE: usize = 40;
+
+pub fn path(level: &Path) -> PathBuf {
+    let mut path = level.as_os_str().to_owned();
+    path.push(".");
+    path.push(EXTENSION);
+
+    PathBuf::from(path)
+}
+
+pub fn parse(contents: &str) -> Result<Vec<Move>, String> {
+    let mut moves = Vec::new();
+
+    for (number, line) in contents.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or_default();
+
+        for letter in line.chars().filter(|c| !c.is_whitespace()) {
+            match Move::from_letter(letter) {
+                Some(m) => moves.push(m),
+                None => return Err(format!("line {}: unknown move `{}`", number + 1, letter)),
+            }
+        }
+    }
+
+    Ok(moves)
+}
+
+pub fn to_string(moves: &[Move]) -> String {
+    let mut contents = String::new();
+
+    for line in moves.chunks(MOVES_PER_LINE) {
+        contents.extend(line.iter().map(|m| m.letter()));
+        contents.push('\n');
+    }
+
+    contents
+}
+
+pub fn load(path: &Path) -> Result<Vec<Move>, String> {
+    let contents =
+        fs::read_to_string(path).map_err(|error| format!("{}: {}", path.display(), error))?;
+
+    parse(&contents).map_err(|error| format!("{}: {}", path.display(), error))
+}
+
+pub fn save(path: &Path, moves: &[Move]) -> GameResult {
+    serialization::write_atomic(path, &to_string(moves))
+}
+
+// Plays the replay on `level_data`. Returns the number of moves if it still solves
+// the level, otherwise what went wrong.
+pub fn check(level_data: &LevelData, moves: &[Move]) -> Result<usize, String> {
+    let game = Game::new(level_data)?;
+    let state = game.play(moves)?;
+
+    match game.solved(&state) {
+        true => Ok(moves.len()),
+        false => Err(String::from(
+            "the level is not solved at the end of the replay",
+        )),
+    }
+}
+
+// Checks the replays of all `levels`, and of all levels with a replay inside
+// directories among them. Prints a line for each, failures are reported together
+// at the end, like with thumbnails.
+pub fn check_files(levels: &[PathBuf]) -> GameResult {
+    let mut files = Vec::new();
+    for level in levels {
+        match level.is_dir() {
+            true => {
+                let found = serialization::level_files(level)?;
+                files.extend(found.into_iter().filter(|level| path(level).is_file()));
+            }
+            false => files.push(level.clone()),
+        }
+    }
+
+    let mut failures = Vec::new();
+
+    for level in files {
+        let result = fs::read_to_string(&level)
+            .map_err(|error| format!("{}: {}", level.display(), error))
+            .and_then(|contents| {
+                let moves = load(&path(&level))?;
+                check(&serialization::from_str(&contents), &moves)
+                    .map_err(|error| format!("{}: {}", level.display(), error))
+            });
+
+        match result {
+            Ok(moves) => println!("{}: solved in {} moves", level.display(), moves),
+            Err(error) => failures.push(error),
+        }
+    }
+
+    match failures.is_empty() {
+        true => Ok(()),
+        false => Err(GameError::CustomError(failures.join("\n"))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A level checked in together with its replay.
+    fn fixture() -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/button_wall")
+    }
+
+    #[test]
+    fn parses_letters_and_skips_comments() {
+        let moves = parse("rD # down\n  L\tu\n# UUU\n").unwrap();
+
+        assert_eq!(moves, [Move::Right, Move::Down, Move::Left, Move::Up]);
+        assert_eq!(parse(&to_string(&moves)).unwrap(), moves);
+    }
+
+    #[test]
+    fn rejects_unknown_moves() {
+        assert_eq!(
+            parse("UD\nUX"),
+            Err(String::from("line 2: unknown move `X`"))
+        );
+    }
+
+    #[test]
+    fn fixture_replay_solves_its_level() {
+        let level_data = serialization::load(&fixture()).unwrap();
+        let moves = load(&path(&fixture())).unwrap();
+
+        assert_eq!(check(&level_data, &moves), Ok(6));
+        assert!(check_files(&[fixture()]).is_ok());
+    }
+
+    #[test]
+    fn check_reports_blocked_and_unfinished_replays() {
+        let level_data = serialization::load(&fixture()).unwrap();
+
+        // Without the box on the button the wall stays closed.
+        let blocked = parse("RRRR").unwrap();
+        assert_eq!(
+            check(&level_data, &blocked),
+            Err(String::from("move 4 (R) is blocked"))
+        );
+
+        let unfinished = parse("RDU").unwrap();
+        assert!(check(&level_data, &unfinished).is_err());
+    }
+}
@@ -1,28 +1,31 @@
-use std::collections::HashMap;
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime},
+};
 
 use ggez::{
-    graphics::{Canvas, DrawParam, Image, Rect},
+    graphics::{
+        Canvas, Color, DrawMode, DrawParam, Image, Mesh, Rect, Text, TextFragment, Transform,
+    },
+    mint::Point2,
     Context, GameError, GameResult,
 };
 
-use super::instances::{
-    collectible::CollectibleType, floor::FloorType, object::ObjectType, wall::Wall ,
-    LayerContent,
+use super::{
+    instances::{
+        collectible::CollectibleType,
+        floor::FloorType,
+        object::ObjectType,
+        wall::{Wall, WallOrientation},
+        ActivatingColor, LayerContent,
+    },
+    palette::Palettes,
+    sprite_manifest::{SpriteManifest, MANIFEST_PATH, SPRITE_NAMES},
 };
 
-#[derive(Debug, Hash, PartialEq, Eq)]
-enum Images {
-    Box,
-    Button,
-    Floor,
-    Ghost,
-    Wall,
-    TeleBox,
-    Win,
-    Teleport,
-}
-
-#[derive(Debug, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum DrawId {
     Ghost,
     Box,
@@ -42,150 +45,313 @@ pub enum DrawId {
     Win,
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug)]
 pub struct Resources {
-    images: HashMap<Images, Image>,
-    draw_id: HashMap<DrawId, (Images, Rect)>,
+    images: HashMap<String, Image>,
+    draw_id: HashMap<DrawId, (String, Rect)>,
+    placeholders: HashMap<DrawId, Mesh>,
+
+    // Modification times of the files in the resources directory.
+    watched: HashMap<PathBuf, SystemTime>,
+    // The files of the last failed reload, so its error is shown only once.
+    rejected: HashMap<PathBuf, SystemTime>,
+    last_watch: Instant,
+
+    pub palettes: Palettes,
 }
 
 impl Resources {
+    const WATCH_INTERVAL: Duration = Duration::from_secs(1);
+
     pub fn new() -> Resources {
-        Resources::default()
+        Resources {
+            images: HashMap::new(),
+            draw_id: HashMap::new(),
+            placeholders: HashMap::new(),
+
+            watched: HashMap::new(),
+            rejected: HashMap::new(),
+            last_watch: Instant::now(),
+
+            palettes: Palettes::new(),
+        }
     }
 
+    // Whatever fails to load is drawn with placeholders, so the editor can
+    // always start. The returned error only tells what is missing.
     pub fn initialize(&mut self, ctx: &Context) -> GameResult {
-        let box_img = Image::from_path(ctx, "/Box.png")?;
-        let button_img = Image::from_path(ctx, "/Button.png")?;
-        let floor_img = Image::from_path(ctx, "/Floor.png")?;
-        let ghost_img = Image::from_path(ctx, "/Ghost.png")?;
-        let wall_img = Image::from_path(ctx, "/Wall.png")?;
-        let telebox_img = Image::from_path(ctx, "/TeleBox.png")?;
-        let win_img = Image::from_path(ctx, "/Win.png")?;
-        let teleport_img = Image::from_path(ctx, "/Teleport.png")?;
-
-        self.images.insert(Images::Box, box_img);
-        self.images.insert(Images::Button, button_img);
-        self.images.insert(Images::Floor, floor_img);
-        self.images.insert(Images::Ghost, ghost_img);
-        self.images.insert(Images::Wall, wall_img);
-        self.images.insert(Images::TeleBox, telebox_img);
-        self.images.insert(Images::Win, win_img);
-        self.images.insert(Images::Teleport, teleport_img);
-
-        self.draw_id.insert(
-            DrawId::Ghost,
-            (Images::Ghost, Rect::new(0.0, 0.0, 0.5, 1.0)),
-        );
-        self.draw_id
-            .insert(DrawId::Box, (Images::Box, Rect::new(0.0, 0.0, 0.5, 1.0)));
-        self.draw_id.insert(
-            DrawId::TeleBox,
-            (Images::TeleBox, Rect::new(0.0, 0.0, 1.0, 1.0)),
-        );
+        for (draw_id, _) in SPRITE_NAMES {
+            self.placeholders
+                .insert(draw_id, Self::placeholder(ctx, draw_id)?);
+        }
 
-        self.draw_id.insert(
-            DrawId::Floor,
-            (Images::Floor, Rect::new(0.0, 0.0, 0.5, 0.3333)),
-        );
-        self.draw_id.insert(
-            DrawId::Floor2,
-            (Images::Floor, Rect::new(0.0, 0.3333, 0.5, 0.3333)),
-        );
-        self.draw_id.insert(
-            DrawId::Floor3,
-            (Images::Floor, Rect::new(0.0, 0.6666, 0.5, 0.3333)),
-        );
+        self.watched = Self::modification_times(ctx.fs.resources_dir());
+        self.load(ctx)
+    }
 
-        self.draw_id.insert(
-            DrawId::Button,
-            (Images::Button, Rect::new(0.0, 0.0, 0.5, 1.0)),
-        );
-        self.draw_id.insert(
-            DrawId::Teleport,
-            (Images::Teleport, Rect::new(0.0, 0.0, 0.5, 1.0)),
-        );
+    // Reloads everything when a file in the resources directory was changed,
+    // added or removed. On failure the last good sprites stay in use.
+    pub fn watch(&mut self, ctx: &Context) -> GameResult {
+        if self.last_watch.elapsed() < Self::WATCH_INTERVAL {
+            return Ok(());
+        }
+        self.last_watch = Instant::now();
 
-        self.draw_id.insert(
-            DrawId::VerticalWallOpened,
-            (Images::Wall, Rect::new(0.0, 0.0, 0.25, 1.0)),
-        );
-        self.draw_id.insert(
-            DrawId::VerticalWallClosed,
-            (Images::Wall, Rect::new(0.25, 0.0, 0.25, 1.0)),
-        );
-        self.draw_id.insert(
-            DrawId::HorizontalWallOpened,
-            (Images::Wall, Rect::new(0.5, 0.0, 0.25, 1.0)),
-        );
-        self.draw_id.insert(
-            DrawId::HorizontalWallClosed,
-            (Images::Wall, Rect::new(0.75, 0.0, 0.25, 1.0)),
-        );
+        let watched = Self::modification_times(ctx.fs.resources_dir());
+        if watched == self.watched {
+            return Ok(());
+        }
 
-        self.draw_id.insert(
-            DrawId::Win, 
-            (Images::Win, Rect::new(0.0, 0.0, 1.0, 1.0))
-        );
+        // Failed reloads are retried until one succeeds, a file may have
+        // been read while it was still being written.
+        match self.reload(ctx) {
+            Ok(()) => {
+                self.watched = watched;
+                self.rejected.clear();
+                Ok(())
+            }
+            Err(_) if watched == self.rejected => Ok(()),
+            Err(error) => {
+                self.rejected = watched;
+                Err(GameError::ResourceLoadError(format!(
+                    "Sprites were not reloaded, keeping the old ones:\n{}",
+                    error
+                )))
+            }
+        }
+    }
+
+    fn modification_times(dir: &Path) -> HashMap<PathBuf, SystemTime> {
+        let Ok(entries) = fs::read_dir(dir) else {
+            return HashMap::new();
+        };
+
+        entries
+            .filter_map(|entry| {
+                let entry = entry.ok()?;
+                let modified = entry.metadata().ok()?.modified().ok()?;
+                Some((entry.path(), modified))
+            })
+            .collect()
+    }
+
+    fn reload(&mut self, ctx: &Context) -> GameResult {
+        let mut loaded = Resources::new();
+        loaded.load(ctx)?;
+
+        self.images = loaded.images;
+        self.draw_id = loaded.draw_id;
 
         Ok(())
     }
 
-    pub fn draw_content(
-        &self,
-        ctx: &Context,
-        canvas: &mut Canvas,
-        content: LayerContent,
-        draw_param: DrawParam,
-    ) -> GameResult {
-        let color;
+    // Loads the images listed in the sprite manifest and resolves every region
+    // to the fraction of its image that ggez expects as a source rect.
+    fn load(&mut self, ctx: &Context) -> GameResult {
+        let manifest = SpriteManifest::load(ctx)?;
+        let mut problems = Vec::new();
+
+        for (name, path) in &manifest.images {
+            match Image::from_path(ctx, path) {
+                Ok(image) => {
+                    self.images.insert(name.clone(), image);
+                }
+                Err(error) => {
+                    problems.push(format!("{}: image `{}`: {}", MANIFEST_PATH, name, error))
+                }
+            }
+        }
+
+        for (draw_id, sprite) in manifest.sprites {
+            let Some(image) = self.images.get(&sprite.image) else {
+                continue;
+            };
+
+            let (width, height) = (image.width() as f32, image.height() as f32);
+            let region = sprite.region;
+
+            if region.x < 0.0
+                || region.y < 0.0
+                || region.right() > width
+                || region.bottom() > height
+            {
+                problems.push(format!(
+                    "{}: {:?} does not fit in image `{}` ({}x{})",
+                    MANIFEST_PATH, draw_id, sprite.image, width, height
+                ));
+                continue;
+            }
+
+            self.draw_id.insert(
+                draw_id,
+                (
+                    sprite.image,
+                    Rect::new(
+                        region.x / width,
+                        region.y / height,
+                        region.w / width,
+                        region.h / height,
+                    ),
+                ),
+            );
+        }
 
-        let draw_id = match content {
+        match problems.is_empty() {
+            true => Ok(()),
+            false => Err(GameError::ResourceLoadError(problems.join("\n"))),
+        }
+    }
+
+    // The sprite of anything but a wall together with the color it is tinted with.
+    pub fn cell_drawing(content: &LayerContent) -> Option<(DrawId, ActivatingColor)> {
+        match content {
             LayerContent::Object(obj) => {
-                color = obj.color;
-                match obj.object_type {
+                let draw_id = match obj.object_type {
                     ObjectType::Player => DrawId::Ghost,
                     ObjectType::Box => DrawId::Box,
                     ObjectType::TeleBox => DrawId::TeleBox,
-                }
+                };
+                Some((draw_id, obj.color))
             }
 
             LayerContent::Floor(flr) => {
-                color = flr.color;
-
-                match flr.floor_type {
+                let draw_id = match flr.floor_type {
                     FloorType::Normal => match flr.durability {
-                        1 => DrawId::Floor,
                         2 => DrawId::Floor2,
-                        3 => DrawId::Floor3,
+                        3.. => DrawId::Floor3,
 
-                        _ => return Ok(()),
+                        _ => DrawId::Floor,
                     },
 
                     FloorType::Button => DrawId::Button,
                     FloorType::Teleport => DrawId::Teleport,
-                }
+                };
+                Some((draw_id, flr.color))
             }
 
-            LayerContent::Wall(wl) => {
-                self.draw_wall(ctx, canvas, wl, draw_param)?;
+            LayerContent::Wall(_) => None,
 
-                return Ok(());
+            LayerContent::Collectible(clct) => {
+                let draw_id = match clct.collectible_type {
+                    CollectibleType::Win => DrawId::Win,
+                };
+                Some((draw_id, clct.color))
             }
+        }
+    }
 
-            LayerContent::Collectible(clct) => {
-                color = clct.color;
+    pub fn wall_draw_id(orientation: WallOrientation, opened: bool) -> DrawId {
+        match (orientation, opened) {
+            (WallOrientation::Right, true) => DrawId::VerticalWallOpened,
+            (WallOrientation::Right, false) => DrawId::VerticalWallClosed,
+            (WallOrientation::Down, true) => DrawId::HorizontalWallOpened,
+            (WallOrientation::Down, false) => DrawId::HorizontalWallClosed,
+        }
+    }
 
-                match clct.collectible_type {
-                    CollectibleType::Win => DrawId::Win,
+    pub fn draw_content(
+        &self,
+        ctx: &Context,
+        canvas: &mut Canvas,
+        content: LayerContent,
+        draw_param: DrawParam,
+    ) -> GameResult {
+        if let LayerContent::Wall(wl) = content {
+            return self.draw_wall(ctx, canvas, wl, draw_param);
+        }
+
+        let Some((draw_id, color)) = Self::cell_drawing(&content) else {
+            return Ok(());
+        };
+
+        // Durabilities that don't have their own sprite are written over it.
+        let label = match &content {
+            LayerContent::Floor(flr) if flr.floor_type == FloorType::Normal => {
+                if flr.is_indestructible() {
+                    Some(String::from("inf"))
+                } else if !(1..=3).contains(&flr.durability) {
+                    Some(flr.durability.to_string())
+                } else {
+                    None
                 }
             }
+            _ => None,
         };
 
-        self.draw_drawing(canvas, draw_id, draw_param.color(color))?;
+        let tint = self.tint(color, draw_param);
+        self.draw_drawing(canvas, draw_id, draw_param.color(tint))?;
+
+        if let Some(label) = label {
+            Self::draw_label(canvas, &label, draw_param);
+        }
+        self.draw_glyph(canvas, color, draw_param, Point2 { x: 5.0, y: 5.0 });
 
         Ok(())
     }
 
+    // Palette color faded by the alpha the caller put into `draw_param`.
+    fn tint(&self, color: ActivatingColor, draw_param: DrawParam) -> Color {
+        let mut tint = self.palettes.color(color);
+        tint.a *= draw_param.color.a;
+
+        tint
+    }
+
+    // Letter of the color centered at `at` inside the cell, with a shadow so it
+    // reads on top of any tint.
+    fn draw_glyph(
+        &self,
+        canvas: &mut Canvas,
+        color: ActivatingColor,
+        draw_param: DrawParam,
+        at: Point2<f32>,
+    ) {
+        let (Some(glyph), Transform::Values { dest, .. }) =
+            (self.palettes.glyph(color), draw_param.transform)
+        else {
+            return;
+        };
+
+        let text = Text::new(TextFragment::new(glyph).scale(8.0));
+
+        let alpha = draw_param.color.a;
+        for (shift, text_color) in [
+            (0.5, Color::new(0.0, 0.0, 0.0, alpha)),
+            (0.0, Color::new(1.0, 1.0, 1.0, alpha)),
+        ] {
+            canvas.draw(
+                &text,
+                DrawParam::default()
+                    .dest(Point2 {
+                        x: dest.x + at.x + shift,
+                        y: dest.y + at.y + shift,
+                    })
+                    .offset(Point2 { x: 0.5, y: 0.5 })
+                    .color(text_color),
+            );
+        }
+    }
+
+    // Small text in the bottom right corner of the cell, drawn over the sprite.
+    fn draw_label(canvas: &mut Canvas, label: &str, draw_param: DrawParam) {
+        let Transform::Values { dest, .. } = draw_param.transform else {
+            return;
+        };
+
+        let text = Text::new(TextFragment::new(label).scale(8.0));
+
+        canvas.draw(
+            &text,
+            DrawParam::default()
+                .dest(Point2 {
+                    x: dest.x + 15.0,
+                    y: dest.y + 16.0,
+                })
+                .offset(Point2 { x: 1.0, y: 1.0 })
+                .color(Color::new(1.0, 1.0, 1.0, draw_param.color.a)),
+        );
+    }
+
     pub fn draw_wall(
         &self,
         ctx: &Context,
@@ -194,35 +360,35 @@ impl Resources {
         draw_param: DrawParam,
     ) -> GameResult {
         if let Some(wl) = wall.right {
-            let draw_id = match match wl.input_dependent {
+            let opened = match wl.input_dependent {
                 true => match wl.opened {
                     true => ctx.time.ticks() % 70 <= 60,
                     false => ctx.time.ticks() % 70 > 60,
-                }
+                },
 
                 false => wl.opened,
-            } {
-                true => DrawId::VerticalWallOpened,
-                false => DrawId::VerticalWallClosed,
             };
+            let draw_id = Self::wall_draw_id(WallOrientation::Right, opened);
 
-            self.draw_drawing(canvas, draw_id, draw_param.color(wl.color))?;
+            let tint = self.tint(wl.color, draw_param);
+            self.draw_drawing(canvas, draw_id, draw_param.color(tint))?;
+            self.draw_glyph(canvas, wl.color, draw_param, Point2 { x: 16.0, y: 8.0 });
         }
 
         if let Some(wl) = wall.down {
-            let draw_id = match match wl.input_dependent {
+            let opened = match wl.input_dependent {
                 true => match wl.opened {
                     true => ctx.time.ticks() % 70 <= 60,
                     false => ctx.time.ticks() % 70 > 60,
-                }
+                },
 
                 false => wl.opened,
-            } {
-                true => DrawId::HorizontalWallOpened,
-                false => DrawId::HorizontalWallClosed,
             };
+            let draw_id = Self::wall_draw_id(WallOrientation::Down, opened);
 
-            self.draw_drawing(canvas, draw_id, draw_param.color(wl.color))?;
+            let tint = self.tint(wl.color, draw_param);
+            self.draw_drawing(canvas, draw_id, draw_param.color(tint))?;
+            self.draw_glyph(canvas, wl.color, draw_param, Point2 { x: 8.0, y: 16.0 });
         }
 
         Ok(())
@@ -234,24 +400,107 @@ impl Resources {
         draw_id: DrawId,
         draw_param: DrawParam,
     ) -> GameResult {
-        let (image, src_rect) =
-            self.draw_id
-                .get(&draw_id)
-                .ok_or(GameError::CustomError(format!(
-                    "Draw ID does not added in Resources, {:?}",
-                    draw_id
-                )))?;
-
-        let image = self
-            .images
-            .get(image)
-            .ok_or(GameError::CustomError(format!(
-                "Image does not added in Resources, {:?}",
-                image
-            )))?;
-
-        canvas.draw(image, draw_param.src(src_rect.clone()));
+        let sprite = self
+            .draw_id
+            .get(&draw_id)
+            .and_then(|(image, src_rect)| Some((self.images.get(image)?, src_rect)));
+
+        match sprite {
+            Some((image, src_rect)) => canvas.draw(image, draw_param.src(src_rect.clone())),
+            None => {
+                if let Some(mesh) = self.placeholders.get(&draw_id) {
+                    canvas.draw(mesh, draw_param);
+                }
+            }
+        }
 
         Ok(())
     }
+
+    // Stand-in for a sprite that could not be loaded, drawn in white so it is tinted
+    // with the entity color like the sprites are.
+    fn placeholder(ctx: &Context, draw_id: DrawId) -> GameResult<Mesh> {
+        let center = Point2 { x: 8.0, y: 8.0 };
+        let shade = |value| Color::new(value, value, value, 1.0);
+
+        let mesh = match draw_id {
+            DrawId::Ghost => {
+                Mesh::new_circle(ctx, DrawMode::fill(), center, 5.0, 0.5, Color::WHITE)?
+            }
+            DrawId::Box => Mesh::new_rectangle(
+                ctx,
+                DrawMode::fill(),
+                Rect::new(2.0, 2.0, 12.0, 12.0),
+                Color::WHITE,
+            )?,
+            DrawId::TeleBox => Mesh::new_rectangle(
+                ctx,
+                DrawMode::stroke(2.0),
+                Rect::new(3.0, 3.0, 10.0, 10.0),
+                Color::WHITE,
+            )?,
+
+            DrawId::Floor | DrawId::Floor2 | DrawId::Floor3 => {
+                let value = match draw_id {
+                    DrawId::Floor => 0.3,
+                    DrawId::Floor2 => 0.45,
+                    _ => 0.6,
+                };
+                Mesh::new_rectangle(
+                    ctx,
+                    DrawMode::fill(),
+                    Rect::new(0.0, 0.0, 16.0, 16.0),
+                    shade(value),
+                )?
+            }
+            DrawId::Button => {
+                Mesh::new_circle(ctx, DrawMode::stroke(2.0), center, 5.0, 0.5, Color::WHITE)?
+            }
+            DrawId::Teleport => Mesh::new_rectangle(
+                ctx,
+                DrawMode::stroke(1.0),
+                Rect::new(1.0, 1.0, 14.0, 14.0),
+                Color::WHITE,
+            )?,
+
+            DrawId::VerticalWallOpened | DrawId::VerticalWallClosed => {
+                let width = match draw_id {
+                    DrawId::VerticalWallClosed => 3.0,
+                    _ => 1.0,
+                };
+                Mesh::new_line(
+                    ctx,
+                    &[Point2 { x: 16.0, y: 0.0 }, Point2 { x: 16.0, y: 16.0 }],
+                    width,
+                    Color::WHITE,
+                )?
+            }
+            DrawId::HorizontalWallOpened | DrawId::HorizontalWallClosed => {
+                let width = match draw_id {
+                    DrawId::HorizontalWallClosed => 3.0,
+                    _ => 1.0,
+                };
+                Mesh::new_line(
+                    ctx,
+                    &[Point2 { x: 0.0, y: 16.0 }, Point2 { x: 16.0, y: 16.0 }],
+                    width,
+                    Color::WHITE,
+                )?
+            }
+
+            DrawId::Win => Mesh::new_polygon(
+                ctx,
+                DrawMode::fill(),
+                &[
+                    Point2 { x: 8.0, y: 2.0 },
+                    Point2 { x: 14.0, y: 8.0 },
+                    Point2 { x: 8.0, y: 14.0 },
+                    Point2 { x: 2.0, y: 8.0 },
+                ],
+                Color::WHITE,
+            )?,
+        };
+
+        Ok(mesh)
+    }
 }
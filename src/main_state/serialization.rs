@@ -1,6 +1,10 @@
-use std::path::Path;
+use std::{
+    fs::File,
+    io::Write,
+    path::{Path, PathBuf},
+};
 
-use ggez::{glam::IVec2, GameResult};
+use ggez::{glam::IVec2, GameError, GameResult};
 
 use super::{
     instances::{
@@ -13,6 +17,8 @@ use super::{
     level_data::LevelData,
 };
 
+pub const EXTENSION: &str = "lvl";
+
 fn write_line(
     content: &mut String,
     name: impl AsRef<str>,
@@ -62,17 +68,37 @@ pub fn write_wall(
 }
 
 pub fn save(level_data: &LevelData, path: &Path) -> GameResult {
+    write_atomic(path, &to_string(level_data))
+}
+
+// Writes to a temporary file next to `path` and renames it over the target,
+// so a crash mid-write never leaves a truncated level behind.
+pub fn write_atomic(path: &Path, contents: &str) -> GameResult {
+    let file_name = path.file_name().ok_or(GameError::CustomError(format!(
+        "Not a file path: {:?}",
+        path
+    )))?;
+    let temp_path = path.with_file_name(format!(".{}.tmp", file_name.to_string_lossy()));
+
+    let written = File::create(&temp_path)
+        .and_then(|mut file| {
+            file.write_all(contents.as_bytes())?;
+            file.sync_all()
+        })
+        .and_then(|_| std::fs::rename(&temp_path, path));
+
+    if let Err(error) = written {
+        let _ = std::fs::remove_file(&temp_path);
+        return Err(error.into());
+    }
+
+    Ok(())
+}
+
+pub fn to_string(level_data: &LevelData) -> String {
     let mut contents = String::new();
-    let mut dimentions = IVec2::new(0, 0);
 
     for (pos, floor) in &level_data.floors {
-        if pos.x > dimentions.x {
-            dimentions.x = pos.x;
-        }
-        if pos.y > dimentions.y {
-            dimentions.y = pos.y;
-        }
-
         let name = match floor.floor_type {
             FloorType::Normal => "floor",
             FloorType::Button => "button",
@@ -93,13 +119,6 @@ pub fn save(level_data: &LevelData, path: &Path) -> GameResult {
     contents.push('\n');
 
     for (pos, object) in &level_data.objects {
-        if pos.x > dimentions.x {
-            dimentions.x = pos.x;
-        }
-        if pos.y > dimentions.y {
-            dimentions.y = pos.y;
-        }
-
         let name = match object.object_type {
             ObjectType::Player => "player",
             ObjectType::Box => "box",
@@ -117,13 +136,6 @@ pub fn save(level_data: &LevelData, path: &Path) -> GameResult {
     contents.push('\n');
 
     for (pos, collectible) in &level_data.collectibles {
-        if pos.x > dimentions.x {
-            dimentions.x = pos.x;
-        }
-        if pos.y > dimentions.y {
-            dimentions.y = pos.y;
-        }
-
         let name = match collectible.collectible_type {
             CollectibleType::Win => "win",
         };
@@ -139,13 +151,6 @@ pub fn save(level_data: &LevelData, path: &Path) -> GameResult {
     contents.push('\n');
 
     for (pos, wall) in &level_data.walls {
-        if pos.x > dimentions.x {
-            dimentions.x = pos.x;
-        }
-        if pos.y > dimentions.y {
-            dimentions.y = pos.y;
-        }
-
         if let Some(wall_data) = &wall.down {
             write_wall(&mut contents, pos, wall_data, "d");
         }
@@ -155,18 +160,46 @@ pub fn save(level_data: &LevelData, path: &Path) -> GameResult {
         }
     }
 
+    let dimentions = level_data.dimensions();
     contents.insert_str(0, &format!("{},{}\n", dimentions.x, dimentions.y));
 
-    std::fs::write(path, contents)?;
-
-    Ok(())
+    contents
 }
 
 pub fn load(path: &Path) -> GameResult<LevelData> {
-    let mut level_data = LevelData::new();
-
     let contents = std::fs::read_to_string(path)?;
 
+    Ok(from_str(&contents))
+}
+
+// Level files directly inside `dir`, sorted by name.
+pub fn level_files(dir: &Path) -> GameResult<Vec<PathBuf>> {
+    let mut levels = std::fs::read_dir(dir)?
+        .filter_map(|entry| Some(entry.ok()?.path()))
+        .filter(|path| is_level_file(path))
+        .collect::<Vec<_>>();
+    levels.sort();
+
+    Ok(levels)
+}
+
+// Levels are saved without an extension or with `.lvl`, everything else in a level
+// directory (thumbnails, replays, configs) is left alone.
+pub fn is_level_file(path: &Path) -> bool {
+    let hidden = path
+        .file_name()
+        .is_none_or(|name| name.to_string_lossy().starts_with('.'));
+    let level = match path.extension() {
+        Some(extension) => extension == EXTENSION,
+        None => true,
+    };
+
+    path.is_file() && !hidden && level
+}
+
+pub fn from_str(contents: &str) -> LevelData {
+    let mut level_data = LevelData::new();
+
     let mut lines = contents.lines();
     lines.next();
 
@@ -232,7 +265,7 @@ pub fn load(path: &Path) -> GameResult<LevelData> {
         level_data.insert(pos, name, orientation);
     }
 
-    Ok(level_data)
+    level_data
 }
 
 fn parse_color(color: &str) -> ActivatingColor {
@@ -272,7 +305,8 @@ fn init_wall<'a>(wall: &mut WallData, props: impl std::iter::Iterator<Item = &'a
         };
         match word {
             "c" => wall.color = parse_color(val),
-            "closed" => wall.opened = parse_bool(val),
+            // Written as the opposite of `opened` by `write_wall`.
+            "closed" => wall.opened = !parse_bool(val),
             "inputbased" => wall.input_dependent = parse_bool(val),
 
             _ => (),
@@ -314,3 +348,44 @@ fn init_collectible<'a>(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn walls_keep_being_closed() {
+        let mut level_data = LevelData::new();
+        level_data.insert(
+            IVec2::new(0, 0),
+            LayerData::Wall(WallData::new(
+                WallType::Normal,
+                ActivatingColor::Red,
+                true,
+                false,
+            )),
+            WallOrientation::Right,
+        );
+        level_data.insert(
+            IVec2::new(1, 0),
+            LayerData::Wall(WallData::new(
+                WallType::Normal,
+                ActivatingColor::None,
+                false,
+                true,
+            )),
+            WallOrientation::Down,
+        );
+
+        assert_eq!(from_str(&to_string(&level_data)), level_data);
+    }
+
+    #[test]
+    fn closed_is_the_opposite_of_opened() {
+        let level_data = from_str("2,1\nwall 0,0 r c:r closed:true inputbased:true\n");
+        let wall = level_data.walls[&IVec2::new(0, 0)].right.as_ref().unwrap();
+
+        assert!(!wall.opened);
+        assert!(wall.input_dependent);
+    }
+}
@@ -0,0 +1,175 @@
+use std::collections::HashMap;
+
+use super::game::{Game, Move, State};
+
+// What a breadth first search over every state of a level found.
+#[derive(Debug, Clone)]
+pub struct Search {
+    // Number of different states reached from the start, solved ones included.
+    pub states: usize,
+    // Whether every reachable state was visited before running into the limit.
+    pub complete: bool,
+    // One of the shortest solutions.
+    pub solution: Option<Vec<Move>>,
+    // States from which the level can't be solved anymore. Only known for
+    // complete searches.
+    pub dead_ends: Option<usize>,
+    // The states along `solution`, starting with the start.
+    pub path: Vec<State>,
+}
+
+// Visits at most `limit` states. Solved states are not played any further.
+pub fn search(game: &Game, limit: usize) -> Search {
+    let mut ids = HashMap::new();
+    let mut states = vec![game.start().clone()];
+    let mut parents: Vec<Option<(usize, Move)>> = vec![None];
+    let mut predecessors: Vec<Vec<usize>> = vec![Vec::new()];
+    let mut solved = Vec::new();
+    let mut complete = true;
+
+    ids.insert(game.start().clone(), 0);
+
+    // `states` doubles as the queue, in the order the states were found.
+    let mut index = 0;
+    while index < states.len() {
+        if game.solved(&states[index]) {
+            solved.push(index);
+            index += 1;
+            continue;
+        }
+
+        for m in Move::ALL {
+            let Some(next) = game.step(&states[index], m) else {
+                continue;
+            };
+
+            let id = match ids.get(&next) {
+                Some(id) => *id,
+                None => {
+                    if states.len() >= limit {
+                        complete = false;
+                        continue;
+                    }
+
+                    let id = states.len();
+                    ids.insert(next.clone(), id);
+                    states.push(next);
+                    parents.push(Some((index, m)));
+                    predecessors.push(Vec::new());
+                    id
+                }
+            };
+
+            predecessors[id].push(index);
+        }
+
+        index += 1;
+    }
+
+    // The first solved state found is one of the closest to the start.
+    let mut solution = None;
+    let mut path = Vec::new();
+    if let Some(first) = solved.first() {
+        let mut moves = Vec::new();
+        let mut id = *first;
+        path.push(states[id].clone());
+
+        while let Some((parent, m)) = parents[id] {
+            moves.push(m);
+            path.push(states[parent].clone());
+            id = parent;
+        }
+
+        moves.reverse();
+        path.reverse();
+        solution = Some(moves);
+    }
+
+    let dead_ends = match complete {
+        true => Some(states.len() - solvable(&predecessors, &solved)),
+        false => None,
+    };
+
+    Search {
+        states: states.len(),
+        complete,
+        solution,
+        dead_ends,
+        path,
+    }
+}
+
+// Counts the states that lead to a solved one, by walking back from the solved ones.
+fn solvable(predecessors: &[Vec<usize>], solved: &[usize]) -> usize {
+    let mut reached = vec![false; predecessors.len()];
+    let mut stack = solved.to_vec();
+    let mut count = 0;
+
+    while let Some(id) = stack.pop() {
+        if reached[id] {
+            continue;
+        }
+        reached[id] = true;
+        count += 1;
+
+        stack.extend(predecessors[id].iter().filter(|id| !reached[**id]));
+    }
+
+    count
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::main_state::serialization;
+
+    #[test]
+    fn finds_a_shortest_solution() {
+        let game = Game::new(&serialization::from_str(
+            "4,2\nfloor 0,0  dur:-1 c:n\nfloor 1,0  dur:-1 c:n\nfloor 2,0  dur:1 c:n\n\
+             floor 3,0  dur:-1 c:n\nfloor 4,0  dur:-1 c:n\nfloor 1,1  dur:-1 c:n\n\
+             button 1,2  dur:-1 c:r\nplayer 0,0  c:n\nbox 1,1  c:n\nwin 4,0  c:n\n\
+             wall 3,0 r c:r closed:true inputbased:true\n",
+        ))
+        .unwrap();
+
+        let search = search(&game, 1000);
+        let solution = search.solution.unwrap();
+
+        assert!(search.complete);
+        assert_eq!(solution.len(), 6);
+        assert!(game.solved(&game.play(&solution).unwrap()));
+        assert_eq!(search.path.len(), solution.len() + 1);
+    }
+
+    #[test]
+    fn unsolvable_levels_are_all_dead_ends() {
+        let game = Game::new(&serialization::from_str(
+            "2,1\nfloor 0,0  dur:-1 c:n\nfloor 1,0  dur:-1 c:n\nplayer 0,0  c:n\n\
+             box 1,0  c:n\nwin 1,0  c:n\n",
+        ))
+        .unwrap();
+
+        let search = search(&game, 1000);
+
+        assert_eq!(search.solution, None);
+        assert_eq!(search.dead_ends, Some(search.states));
+    }
+
+    #[test]
+    fn stops_at_the_limit() {
+        let mut contents = String::from("9,9\nplayer 0,0  c:n\nwin 8,8  c:n\n");
+        for y in 0..9 {
+            for x in 0..9 {
+                contents.push_str(&format!("floor {},{}  dur:-1 c:n\n", x, y));
+            }
+        }
+        let game = Game::new(&serialization::from_str(&contents)).unwrap();
+
+        let search = search(&game, 10);
+
+        assert_eq!(search.states, 10);
+        assert!(!search.complete);
+        assert_eq!(search.dead_ends, None);
+    }
+}
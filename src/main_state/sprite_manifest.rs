@@ -0,0 +1,234 @@
+use std::{collections::HashMap, io::Read};
+
+use ggez::{graphics::Rect, Context, GameError, GameResult};
+
+use super::resources::DrawId;
+
+pub const MANIFEST_PATH: &str = "/sprites.toml";
+
+// Names under which every entity state is looked up in the `[sprites]` table.
+pub const SPRITE_NAMES: [(DrawId, &str); 13] = [
+    (DrawId::Ghost, "object.player"),
+    (DrawId::Box, "object.box"),
+    (DrawId::TeleBox, "object.telebox"),
+    (DrawId::Floor, "floor.normal.1"),
+    (DrawId::Floor2, "floor.normal.2"),
+    (DrawId::Floor3, "floor.normal.3"),
+    (DrawId::Button, "floor.button"),
+    (DrawId::Teleport, "floor.teleport"),
+    (DrawId::HorizontalWallOpened, "wall.horizontal.opened"),
+    (DrawId::HorizontalWallClosed, "wall.horizontal.closed"),
+    (DrawId::VerticalWallOpened, "wall.vertical.opened"),
+    (DrawId::VerticalWallClosed, "wall.vertical.closed"),
+    (DrawId::Win, "collectible.win"),
+];
+
+// A region of an image in pixels.
+#[derive(Debug, Clone)]
+pub struct Sprite {
+    pub image: String,
+    pub region: Rect,
+}
+
+#[derive(Debug, Default)]
+pub struct SpriteManifest {
+    // Image name and its path inside the resources directory.
+    pub images: Vec<(String, String)>,
+    pub sprites: HashMap<DrawId, Sprite>,
+}
+
+impl SpriteManifest {
+    pub fn load(ctx: &Context) -> GameResult<SpriteManifest> {
+        let mut contents = String::new();
+        ctx.fs
+            .open(MANIFEST_PATH)
+            .and_then(|mut file| Ok(file.read_to_string(&mut contents)?))
+            .map_err(|error| {
+                GameError::ResourceLoadError(format!("{}: {}", MANIFEST_PATH, error))
+            })?;
+
+        SpriteManifest::parse(&contents).map_err(|problems| {
+            GameError::ResourceLoadError(
+                problems
+                    .iter()
+                    .map(|problem| format!("{}: {}", MANIFEST_PATH, problem))
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+            )
+        })
+    }
+
+    // Collects every problem in the manifest instead of stopping at the first one.
+    pub fn parse(contents: &str) -> Result<SpriteManifest, Vec<String>> {
+        let manifest = contents
+            .parse::<toml::Value>()
+            .map_err(|error| vec![error.to_string()])?;
+
+        let mut problems = Vec::new();
+        let mut images = Vec::new();
+        let mut regions = HashMap::new();
+        let mut sprites = HashMap::new();
+
+        let grid = match manifest.get("grid") {
+            Some(grid) => grid.as_integer().unwrap_or_else(|| {
+                problems.push(String::from("`grid` must be a number of pixels"));
+                1
+            }),
+            None => 1,
+        } as f32;
+
+        let table = |name: &str, problems: &mut Vec<String>| {
+            let table = manifest.get(name).and_then(toml::Value::as_table);
+            if table.is_none() {
+                problems.push(format!("missing [{}] table", name));
+            }
+            table.cloned().unwrap_or_default()
+        };
+
+        for (name, path) in table("images", &mut problems) {
+            match path.as_str() {
+                Some(path) => images.push((name, String::from(path))),
+                None => problems.push(format!("image `{}` must be a path", name)),
+            }
+        }
+
+        for (name, region) in table("regions", &mut problems) {
+            let image = region.get("image").and_then(toml::Value::as_str);
+            let (rect, scale) = match (region.get("pixels"), region.get("grid")) {
+                (Some(rect), None) => (rect, 1.0),
+                (None, Some(rect)) => (rect, grid),
+                _ => {
+                    problems.push(format!(
+                        "region `{}` needs exactly one of `pixels` or `grid`",
+                        name
+                    ));
+                    continue;
+                }
+            };
+
+            let Some(image) = image else {
+                problems.push(format!("region `{}` has no image", name));
+                continue;
+            };
+            if !images.iter().any(|(image_name, _)| image_name == image) {
+                problems.push(format!("region `{}` uses unknown image `{}`", name, image));
+                continue;
+            }
+
+            let Some(rect) = parse_rect(rect) else {
+                problems.push(format!("region `{}` must be [x, y, width, height]", name));
+                continue;
+            };
+
+            regions.insert(
+                name,
+                Sprite {
+                    image: String::from(image),
+                    region: Rect::new(
+                        rect.x * scale,
+                        rect.y * scale,
+                        rect.w * scale,
+                        rect.h * scale,
+                    ),
+                },
+            );
+        }
+
+        let sprite_table = table("sprites", &mut problems);
+
+        for (name, _) in &sprite_table {
+            if !SPRITE_NAMES.iter().any(|(_, known)| known == name) {
+                problems.push(format!("unknown sprite `{}`", name));
+            }
+        }
+
+        for (draw_id, name) in SPRITE_NAMES {
+            let Some(region) = sprite_table.get(name) else {
+                problems.push(format!("no sprite for `{}`", name));
+                continue;
+            };
+
+            match region.as_str().and_then(|region| regions.get(region)) {
+                Some(sprite) => {
+                    sprites.insert(draw_id, sprite.clone());
+                }
+                None => problems.push(format!("sprite `{}` uses unknown region {}", name, region)),
+            }
+        }
+
+        match problems.is_empty() {
+            true => Ok(SpriteManifest { images, sprites }),
+            false => Err(problems),
+        }
+    }
+}
+
+fn parse_rect(value: &toml::Value) -> Option<Rect> {
+    let values = value
+        .as_array()?
+        .iter()
+        .map(|value| match value {
+            toml::Value::Integer(value) => Some(*value as f32),
+            toml::Value::Float(value) => Some(*value as f32),
+            _ => None,
+        })
+        .collect::<Option<Vec<_>>>()?;
+
+    match values[..] {
+        [x, y, w, h] => Some(Rect::new(x, y, w, h)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHIPPED: &str = include_str!("../../resources/sprites.toml");
+
+    #[test]
+    fn the_shipped_manifest_parses() {
+        let manifest = SpriteManifest::parse(SHIPPED).unwrap();
+
+        assert_eq!(manifest.sprites.len(), SPRITE_NAMES.len());
+        let floor = &manifest.sprites[&DrawId::Floor2];
+        assert_eq!(floor.image, "floor");
+        assert_eq!(floor.region, Rect::new(0.0, 16.0, 16.0, 16.0));
+        let wall = &manifest.sprites[&DrawId::HorizontalWallClosed];
+        assert_eq!(wall.region, Rect::new(96.0, 0.0, 32.0, 32.0));
+    }
+
+    #[test]
+    fn reports_missing_sprites() {
+        let contents = SHIPPED.replace("\"collectible.win\" = \"win\"", "");
+        let problems = SpriteManifest::parse(&contents).unwrap_err();
+
+        assert_eq!(problems, ["no sprite for `collectible.win`"]);
+    }
+
+    #[test]
+    fn reports_unknown_regions() {
+        let contents = SHIPPED.replace("\"object.box\" = \"box\"", "\"object.box\" = \"crate\"");
+        let problems = SpriteManifest::parse(&contents).unwrap_err();
+
+        assert_eq!(
+            problems,
+            ["sprite `object.box` uses unknown region \"crate\""]
+        );
+    }
+
+    #[test]
+    fn reports_bad_rects() {
+        let contents = SHIPPED
+            .replace("grid = [0, 0, 1, 1] }", "grid = [0, 0, 1] }")
+            .replace("pixels = [0, 0, 32, 32]", "pixels = [0, \"0\", 32, 32]");
+        let problems = SpriteManifest::parse(&contents).unwrap_err();
+
+        assert!(problems.contains(&String::from(
+            "region `ghost` must be [x, y, width, height]"
+        )));
+        assert!(problems.contains(&String::from(
+            "region `wall_vertical_opened` must be [x, y, width, height]"
+        )));
+    }
+}